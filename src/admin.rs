@@ -0,0 +1,117 @@
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+use log::{info, warn, debug};
+use quick_xml::events::{Event as XmlEvent, BytesStart};
+use quick_xml::Writer;
+use crate::protocol::{Event, Request, Slot};
+use crate::util::{Element, Error, Result, XmlReader};
+
+/// A client for administrating the server, e.g. to prepare
+/// matches with reserved slots and to observe their games.
+pub struct AdminClient<R, W> where R: Read, W: Write {
+    reader: XmlReader<BufReader<R>>,
+    writer: Writer<BufWriter<W>>,
+}
+
+impl AdminClient<TcpStream, TcpStream> {
+    /// Connects to the server at the given address via TCP
+    /// and authenticates with the given password.
+    pub fn connect(host: &str, port: u16, password: &str) -> Result<Self> {
+        let address = format!("{}:{}", host, port);
+        let stream = TcpStream::connect(&address)?;
+        info!("Connected to {}", address);
+        Self::new(stream.try_clone()?, stream, password)
+    }
+}
+
+impl<R, W> AdminClient<R, W> where R: Read, W: Write {
+    /// Performs the handshake over the given streams
+    /// and authenticates with the given password.
+    pub fn new(read: R, write: W, password: &str) -> Result<Self> {
+        let mut client = Self {
+            reader: XmlReader::new(BufReader::new(read)),
+            writer: Writer::new(BufWriter::new(write)),
+        };
+
+        // Write <protocol> and authenticate
+        client.writer.write_event(XmlEvent::Start(BytesStart::borrowed_name(b"protocol")))?;
+        client.send(Request::Authenticate { password: password.to_owned() })?;
+
+        // Read <protocol>
+        client.reader.read_root("protocol")?;
+        info!("Performed handshake");
+        Ok(client)
+    }
+
+    /// Sends the given request to the server.
+    pub fn send(&mut self, request: Request) -> Result<()> {
+        let xml = Element::from(request);
+        debug!("Sending {}", xml);
+        xml.write_to(&mut self.writer)
+    }
+
+    /// Reads the next event from the server, along with its raw XML.
+    /// Errors reported by the server are returned as `Error::ServerError`,
+    /// malformed messages as `Error::Malformed` and can be skipped.
+    pub fn next_event(&mut self) -> Result<(Event, Element)> {
+        let xml = self.reader.read_element()?;
+        debug!("Got event {}", xml);
+        let event = Event::try_from(&xml)?;
+        Ok((event, xml))
+    }
+
+    /// Prepares a game with the given slots and waits for the
+    /// server to answer with the room id and reservation codes.
+    pub fn prepare(&mut self, slots: Vec<Slot>, pause: bool) -> Result<(String, Vec<String>)> {
+        self.send(Request::Prepare { slots, pause })?;
+        loop {
+            match self.next_event() {
+                Ok((Event::Prepared { room_id, reservations }, _)) => return Ok((room_id, reservations)),
+                Ok((event, _)) => warn!("Got unexpected event {:?} while preparing", event),
+                Err(Error::UnknownElement(element)) => warn!("Got unknown tag <{}>: {}", element.name(), element),
+                Err(Error::Malformed { position, message }) => warn!("Skipping malformed message at {}: {}", position, message),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Observes the game in the given room, whose events
+    /// can then be read with `next_event`.
+    pub fn observe(&mut self, room_id: &str) -> Result<()> {
+        self.send(Request::Observe { room_id: room_id.to_owned() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::protocol::{Event, Slot};
+
+    use super::AdminClient;
+
+    #[test]
+    fn test_prepare() {
+        let input = r#"
+            <protocol>
+                <roomCreated roomId="abc" />
+                <prepared roomId="abc">
+                    <reservation>r1</reservation>
+                    <reservation>r2</reservation>
+                </prepared>
+                <observed roomId="abc" />
+        "#;
+        let mut output = Vec::new();
+        let mut client = AdminClient::new(Cursor::new(input), &mut output, "secret").unwrap();
+        let (room_id, reservations) = client.prepare(vec![Slot::new("One", true, true), Slot::new("Two", true, true)], false).unwrap();
+        assert_eq!(room_id, "abc");
+        assert_eq!(reservations, vec!["r1", "r2"]);
+        client.observe(&room_id).unwrap();
+        assert!(matches!(client.next_event().unwrap().0, Event::Observed { .. }));
+        drop(client);
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with(r#"<protocol><authenticate password="secret"/>"#));
+        assert!(output.contains(r#"<observe roomId="abc"/>"#));
+    }
+}
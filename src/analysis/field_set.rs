@@ -0,0 +1,151 @@
+use std::ops::{BitAnd, BitOr, Not, Sub};
+
+use crate::game::{Board, Vec2, Doubled, BOARD_FIELDS};
+
+/// A set of fields on the board, stored as a bitmask
+/// using the same field indices as the board.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldSet(u64);
+
+impl FieldSet {
+    /// The empty set.
+    pub const EMPTY: Self = Self(0);
+
+    /// The set of all fields.
+    pub const ALL: Self = Self(u64::MAX);
+
+    /// Creates a set from the given raw bitmask.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// The raw bitmask.
+    pub const fn bits(self) -> u64 { self.0 }
+
+    /// Creates a set containing a single field index.
+    pub const fn single(index: usize) -> Self {
+        Self(1 << index)
+    }
+
+    /// Whether the given field index is in the set.
+    pub const fn contains_index(self, index: usize) -> bool {
+        self.0 & (1 << index) != 0
+    }
+
+    /// Whether the given field is in the set.
+    pub fn contains(self, coords: impl Into<Vec2<Doubled>>) -> bool {
+        self.contains_index(Board::index_for(coords))
+    }
+
+    /// Adds the given field index to the set.
+    pub fn insert_index(&mut self, index: usize) {
+        self.0 |= 1 << index;
+    }
+
+    /// Adds the given field to the set.
+    pub fn insert(&mut self, coords: impl Into<Vec2<Doubled>>) {
+        self.insert_index(Board::index_for(coords));
+    }
+
+    /// Removes the given field from the set.
+    pub fn remove(&mut self, coords: impl Into<Vec2<Doubled>>) {
+        self.0 &= !(1 << Board::index_for(coords));
+    }
+
+    /// The number of fields in the set.
+    pub const fn len(self) -> usize { self.0.count_ones() as usize }
+
+    /// Whether the set is empty.
+    pub const fn is_empty(self) -> bool { self.0 == 0 }
+
+    /// Iterates the field indices in the set in ascending order.
+    pub fn indices(self) -> impl Iterator<Item=usize> {
+        let mut bits = self.0;
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                None
+            } else {
+                let index = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(index)
+            }
+        })
+    }
+
+    /// Iterates the fields in the set in board order.
+    pub fn iter(self) -> impl Iterator<Item=Vec2<Doubled>> {
+        self.indices().map(|i| Board::coords_for(i).into())
+    }
+
+    /// The total number of fish on the fields in the set.
+    pub fn fish(self, board: &Board) -> usize {
+        self.iter().map(|c| board[c].fish()).sum()
+    }
+}
+
+impl FromIterator<Vec2<Doubled>> for FieldSet {
+    fn from_iter<I: IntoIterator<Item=Vec2<Doubled>>>(iter: I) -> Self {
+        let mut set = Self::EMPTY;
+        for coords in iter {
+            set.insert(coords);
+        }
+        set
+    }
+}
+
+impl BitOr for FieldSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self { Self(self.0 | rhs.0) }
+}
+
+impl BitAnd for FieldSet {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self { Self(self.0 & rhs.0) }
+}
+
+impl Sub for FieldSet {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self { Self(self.0 & !rhs.0) }
+}
+
+impl Not for FieldSet {
+    type Output = Self;
+
+    fn not(self) -> Self { Self(!self.0) }
+}
+
+// The bitmask representation relies on the board fitting into a u64.
+const _: () = assert!(BOARD_FIELDS <= u64::BITS as usize);
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Vec2, Direct, Doubled};
+
+    use super::FieldSet;
+
+    #[test]
+    fn test_insert_and_iter() {
+        let mut set = FieldSet::EMPTY;
+        set.insert(Vec2::<Direct>::new(7, 7));
+        set.insert(Vec2::<Direct>::new(0, 0));
+        set.insert(Vec2::<Direct>::new(3, 1));
+        set.insert(Vec2::<Direct>::new(3, 1));
+
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(Vec2::<Doubled>::new(7, 1)));
+        assert!(!set.contains(Vec2::<Doubled>::new(5, 1)));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![
+            Vec2::<Doubled>::new(0, 0),
+            Vec2::<Doubled>::new(7, 1),
+            Vec2::<Doubled>::new(15, 7),
+        ]);
+
+        set.remove(Vec2::<Direct>::new(0, 0));
+        assert_eq!(set.len(), 2);
+        assert_eq!((set - set).len(), 0);
+        assert_eq!((!set).len(), 62);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::game::{Board, State, Team, Move, BOARD_FIELDS, TEAMS};
+
+use super::FieldSet;
+
+/// The adjacency graph of the remaining ice floes, i.e. the fields
+/// with fish, connected along the six hex directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloeGraph {
+    nodes: FieldSet,
+    /// The floes adjacent to each field (including fields that are not floes).
+    adjacency: [FieldSet; BOARD_FIELDS],
+}
+
+/// A connected group of floes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Component {
+    /// The floes in the component.
+    pub fields: FieldSet,
+    /// The total fish in the component.
+    pub fish: usize,
+    /// The number of penguins per team bordering the component.
+    pub penguins: [usize; TEAMS],
+}
+
+impl Component {
+    /// The team that borders the component alone, if any.
+    pub fn owner(&self) -> Option<Team> {
+        match self.penguins {
+            [0, 0] => None,
+            [_, 0] => Some(Team::One),
+            [0, _] => Some(Team::Two),
+            _ => None,
+        }
+    }
+}
+
+/// A move onto a floe whose removal splits the ice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cut {
+    /// The cutting move.
+    pub m: Move,
+    /// The components after the move.
+    pub components: Vec<Component>,
+    /// The change in fish the moving team borders exclusively,
+    /// minus the change for the opponent.
+    pub gain: i32,
+}
+
+impl FloeGraph {
+    /// Builds the floe graph of the given board.
+    pub fn of(board: &Board) -> Self {
+        let mut nodes = FieldSet::EMPTY;
+        let mut adjacency = [FieldSet::EMPTY; BOARD_FIELDS];
+        for (c, f) in board.fields() {
+            if f.fish() > 0 {
+                nodes.insert(c);
+            }
+        }
+        for (i, (c, _)) in board.fields().enumerate() {
+            for n in c.hex_neighbors() {
+                if Board::in_bounds(n) && nodes.contains(n) {
+                    adjacency[i].insert(n);
+                }
+            }
+        }
+        Self { nodes, adjacency }
+    }
+
+    /// The floes in the graph.
+    pub fn nodes(&self) -> FieldSet { self.nodes }
+
+    /// The floes adjacent to the field with the given index.
+    pub fn neighbors(&self, index: usize) -> FieldSet { self.adjacency[index] }
+
+    /// The floes adjacent to any field in the given set.
+    pub fn neighborhood(&self, fields: FieldSet) -> FieldSet {
+        fields.indices().fold(FieldSet::EMPTY, |acc, i| acc | self.adjacency[i])
+    }
+
+    /// Splits the floes into connected components, ignoring the given fields.
+    pub fn components(&self, without: FieldSet) -> Vec<FieldSet> {
+        let mut remaining = self.nodes - without;
+        let mut components = Vec::new();
+        while let Some(start) = remaining.indices().next() {
+            let mut component = FieldSet::single(start);
+            loop {
+                let grown = (component | self.neighborhood(component)) & remaining;
+                if grown == component {
+                    break;
+                }
+                component = grown;
+            }
+            remaining = remaining - component;
+            components.push(component);
+        }
+        components
+    }
+
+    /// Finds the floes whose removal disconnects their component,
+    /// using Tarjan's lowlink algorithm.
+    pub fn articulation_points(&self) -> FieldSet {
+        let mut search = Tarjan {
+            graph: self,
+            order: [0; BOARD_FIELDS],
+            low: [0; BOARD_FIELDS],
+            counter: 0,
+            points: FieldSet::EMPTY,
+        };
+        for root in self.nodes.indices() {
+            if search.order[root] == 0 {
+                search.visit(root, None);
+            }
+        }
+        search.points
+    }
+}
+
+/// The state of a depth-first search for articulation points.
+struct Tarjan<'a> {
+    graph: &'a FloeGraph,
+    /// The discovery order of each node, starting at 1 (0 means unvisited).
+    order: [u8; BOARD_FIELDS],
+    low: [u8; BOARD_FIELDS],
+    counter: u8,
+    points: FieldSet,
+}
+
+impl<'a> Tarjan<'a> {
+    fn visit(&mut self, node: usize, parent: Option<usize>) {
+        self.counter += 1;
+        self.order[node] = self.counter;
+        self.low[node] = self.counter;
+        let mut children = 0;
+        for next in self.graph.neighbors(node).indices() {
+            if self.order[next] == 0 {
+                children += 1;
+                self.visit(next, Some(node));
+                self.low[node] = self.low[node].min(self.low[next]);
+                if parent.is_some() && self.low[next] >= self.order[node] {
+                    self.points.insert_index(node);
+                }
+            } else if Some(next) != parent {
+                self.low[node] = self.low[node].min(self.order[next]);
+            }
+        }
+        if parent.is_none() && children > 1 {
+            self.points.insert_index(node);
+        }
+    }
+}
+
+/// Describes the components of the given board with their fish
+/// and bordering penguins.
+pub fn components(board: &Board) -> Vec<Component> {
+    let graph = FloeGraph::of(board);
+    graph.components(FieldSet::EMPTY)
+        .into_iter()
+        .map(|fields| {
+            let mut penguins = [0; TEAMS];
+            for (c, team) in board.penguins() {
+                if !(graph.neighbors(Board::index_for(c)) & fields).is_empty() {
+                    penguins[team.index()] += 1;
+                }
+            }
+            Component { fields, fish: fields.fish(board), penguins }
+        })
+        .collect()
+}
+
+/// The fish bordered exclusively by the given team minus the fish
+/// bordered exclusively by the opponent.
+fn exclusive_fish(components: &[Component], team: Team) -> i32 {
+    components.iter()
+        .map(|c| match c.owner() {
+            Some(t) if t == team => c.fish as i32,
+            Some(_) => -(c.fish as i32),
+            None => 0,
+        })
+        .sum()
+}
+
+/// Finds the moves of the given team that land on an articulation
+/// point and thus split the ice.
+pub fn cut_opportunities(state: &State, team: Team) -> Vec<Cut> {
+    let board = state.board();
+    let points = FloeGraph::of(board).articulation_points();
+    if points.is_empty() {
+        return Vec::new();
+    }
+    let before = exclusive_fish(&components(board), team);
+    let moves = if state.current_team() == team {
+        state.possible_moves()
+    } else {
+        state.opponent_moves()
+    };
+    moves.into_iter()
+        .filter(|m| points.contains(m.to()))
+        .map(|m| {
+            let mut child = *board;
+            if let Some(from) = m.from() {
+                child[from] = Default::default();
+            }
+            child[m.to()].place(team);
+            let components = components(&child);
+            let gain = exclusive_fish(&components, team) - before;
+            Cut { m, components, gain }
+        })
+        .collect()
+}
+
+/// Reorders the given moves such that moves onto articulation points
+/// come first, keeping the relative order otherwise.
+pub fn cut_moves_first(board: &Board, moves: &mut [Move]) {
+    let points = FloeGraph::of(board).articulation_points();
+    if !points.is_empty() {
+        moves.sort_by_key(|m| !points.contains(m.to()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::game::{Board, Move, State, Team, Vec2, Direct};
+
+    use super::{FloeGraph, FieldSet, components, cut_opportunities, cut_moves_first};
+
+    fn board() -> Board {
+        // Two triangles of floes connected by a single bridge at (2, 0)
+        indoc! {r#"
+            22122000
+            20020000
+            00000000
+            00000000
+            00000000
+            00000000
+            00000000
+            R000000B
+        "#}.parse().unwrap()
+    }
+
+    #[test]
+    fn test_components() {
+        let graph = FloeGraph::of(&board());
+        assert_eq!(graph.nodes().len(), 7);
+        assert_eq!(graph.components(FieldSet::EMPTY).len(), 1);
+
+        let mut bridge = FieldSet::EMPTY;
+        bridge.insert(Vec2::<Direct>::new(2, 0));
+        let split = graph.components(bridge);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![3, 3]);
+    }
+
+    #[test]
+    fn test_articulation_points() {
+        let points = FloeGraph::of(&board()).articulation_points();
+        assert_eq!(points.len(), 3);
+        assert!(points.contains(Vec2::<Direct>::new(1, 0)));
+        assert!(points.contains(Vec2::<Direct>::new(2, 0)));
+        assert!(points.contains(Vec2::<Direct>::new(3, 0)));
+        assert!(!points.contains(Vec2::<Direct>::new(0, 0)));
+    }
+
+    #[test]
+    fn test_components_with_penguins() {
+        let components = components(&board());
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].fish, 13);
+        assert_eq!(components[0].penguins, [0, 0]);
+    }
+
+    #[test]
+    fn test_cut_moves_first() {
+        let mut moves = vec![
+            Move::placing(Vec2::<Direct>::new(0, 0)),
+            Move::placing(Vec2::<Direct>::new(0, 1)),
+            Move::placing(Vec2::<Direct>::new(2, 0)),
+        ];
+        cut_moves_first(&board(), &mut moves);
+        assert_eq!(moves[0].to(), Vec2::<Direct>::new(2, 0).into());
+        assert_eq!(moves[1].to(), Vec2::<Direct>::new(0, 0).into());
+    }
+
+    #[test]
+    fn test_cut_opportunities() {
+        // The board above with a red penguin next to the bridge, a blue one
+        // bordering the right triangle and the others stuck on the water
+        let board: Board = indoc! {r#"
+            22122000
+            2R02B000
+            00000000
+            00000000
+            00000000
+            B0B0B000
+            00000000
+            R0R0R000
+        "#}.parse().unwrap();
+        let before = components(&board);
+        assert_eq!(before.len(), 1);
+        assert_eq!(before[0].penguins, [1, 1]);
+
+        let state = State::new(board, 8, [0, 0], None, Team::One);
+        let mut cuts = cut_opportunities(&state, Team::One);
+        cuts.sort_by_key(|c| -c.gain);
+        assert_eq!(cuts.len(), 2);
+
+        // Taking the bridge leaves the left triangle to red alone
+        let bridge = &cuts[0];
+        assert_eq!(bridge.m.to(), Vec2::<Direct>::new(2, 0).into());
+        assert_eq!(bridge.gain, 6);
+        let mut split = bridge.components.clone();
+        split.sort_by_key(|c| c.penguins);
+        assert_eq!(split.iter().map(|c| (c.fish, c.penguins, c.owner())).collect::<Vec<_>>(), vec![
+            (6, [1, 0], Some(Team::One)),
+            (6, [1, 1], None),
+        ]);
+
+        // Moving onto the tip of the left triangle leaves red its other two floes
+        let tip = &cuts[1];
+        assert_eq!(tip.m.to(), Vec2::<Direct>::new(1, 0).into());
+        assert_eq!(tip.gain, 4);
+
+        // Blue cannot reach an articulation point
+        assert!(cut_opportunities(&state, Team::Two).is_empty());
+    }
+}
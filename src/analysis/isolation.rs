@@ -0,0 +1,161 @@
+use arrayvec::ArrayVec;
+
+use crate::game::{Board, State, Team, Vec2, Doubled, PENGUINS_PER_TEAM};
+
+use super::{Distances, FieldSet, Reachability};
+
+/// The number of fields up to which a region counts as small.
+pub const SMALL_REGION: usize = 6;
+
+/// The region a single penguin can still reach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Confinement {
+    /// The position of the penguin.
+    pub penguin: Vec2<Doubled>,
+    /// The team of the penguin.
+    pub team: Team,
+    /// The fields reachable by the penguin.
+    pub region: FieldSet,
+    /// The total fish in the region.
+    pub fish: usize,
+    /// Whether an opposing penguin can reach the region too.
+    pub shared: bool,
+}
+
+impl Confinement {
+    /// Computes the region of the given penguin.
+    pub fn of(board: &Board, penguin: Vec2<Doubled>, team: Team) -> Self {
+        let rival = Distances::of_team(board, team.opponent()).reachable();
+        Self::with_rival(board, penguin, team, rival)
+    }
+
+    /// Computes the region of the given penguin, given the fields the opponent can reach.
+    pub(super) fn with_rival(board: &Board, penguin: Vec2<Doubled>, team: Team, rival: FieldSet) -> Self {
+        let region = Distances::of_penguin(board, penguin).reachable();
+        Self {
+            penguin,
+            team,
+            region,
+            fish: region.fish(board),
+            shared: !(region & rival).is_empty(),
+        }
+    }
+
+    /// Whether the penguin cannot move at all.
+    pub fn is_stuck(&self) -> bool { self.region.is_empty() }
+
+    /// Whether the penguin is confined to at most `SMALL_REGION` fields.
+    pub fn is_trapped(&self) -> bool { self.region.len() <= SMALL_REGION }
+
+    /// Whether the penguin is trapped in a region no opponent can reach,
+    /// i.e. its remaining fish are safe.
+    pub fn is_isolated(&self) -> bool { self.is_trapped() && !self.shared }
+}
+
+/// Computes the regions of all penguins of the given team.
+pub fn confinements(board: &Board, team: Team) -> ArrayVec<Confinement, PENGUINS_PER_TEAM> {
+    let rival = Distances::of_team(board, team.opponent()).reachable();
+    board.penguins()
+        .filter(|&(_, t)| t == team)
+        .map(|(c, _)| Confinement::with_rival(board, c, team, rival))
+        .take(PENGUINS_PER_TEAM)
+        .collect()
+}
+
+/// The number of penguins of the given team that are not trapped yet,
+/// but would be after some move of the opponent.
+pub fn threatened_penguins(state: &State, team: Team) -> usize {
+    if !state.penguins_placed() {
+        return 0;
+    }
+    let free: ArrayVec<_, PENGUINS_PER_TEAM> = confinements(state.board(), team)
+        .into_iter()
+        .filter(|c| !c.is_trapped())
+        .map(|c| c.penguin)
+        .collect();
+    if free.is_empty() {
+        return 0;
+    }
+
+    let mut threatened = FieldSet::EMPTY;
+    let opponent_moves = if state.current_team() == team {
+        state.opponent_moves()
+    } else {
+        state.possible_moves()
+    };
+    for m in opponent_moves {
+        // The moving penguin leaves its floe behind and occupies the target.
+        let mut board = *state.board();
+        if let Some(from) = m.from() {
+            board[from] = Default::default();
+        }
+        board[m.to()].place(team.opponent());
+        for &penguin in &free {
+            if Distances::of_penguin(&board, penguin).reachable().len() <= SMALL_REGION {
+                threatened.insert(penguin);
+            }
+        }
+    }
+    threatened.len()
+}
+
+/// Whether the position is tactically sharp enough to search one ply
+/// deeper, i.e. a penguin fights over a small region with an opponent.
+pub fn extension_trigger(board: &Board) -> bool {
+    Reachability::of(board).trapped_penguins(board) != [0, 0]
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::game::{Board, Team, Vec2, Direct};
+
+    use super::{confinements, extension_trigger};
+
+    #[test]
+    fn test_isolated_penguin() {
+        let board: Board = indoc! {r#"
+            R3000000
+            00000000
+            00000000
+            00111111
+            11111111
+            11111111
+            11111111
+            B111111B
+        "#}.parse().unwrap();
+
+        let ones = confinements(&board, Team::One);
+        assert_eq!(ones.len(), 1);
+        assert_eq!(ones[0].penguin, Vec2::<Direct>::new(0, 0).into());
+        assert_eq!(ones[0].region.len(), 1);
+        assert_eq!(ones[0].fish, 3);
+        assert!(ones[0].is_isolated());
+
+        let twos = confinements(&board, Team::Two);
+        assert_eq!(twos.len(), 2);
+        assert!(twos.iter().all(|c| !c.is_trapped()));
+        assert!(!extension_trigger(&board));
+    }
+
+    #[test]
+    fn test_shared_region() {
+        let board: Board = indoc! {r#"
+            R21B0000
+            00000000
+            00000000
+            00000000
+            00000000
+            00000000
+            00000000
+            00000000
+        "#}.parse().unwrap();
+
+        let ones = confinements(&board, Team::One);
+        assert!(ones[0].is_trapped());
+        assert!(ones[0].shared);
+        assert!(!ones[0].is_isolated());
+        assert!(extension_trigger(&board));
+    }
+}
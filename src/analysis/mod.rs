@@ -0,0 +1,9 @@
+mod field_set;
+mod graph;
+mod isolation;
+mod reachability;
+
+pub use field_set::*;
+pub use graph::*;
+pub use isolation::*;
+pub use reachability::*;
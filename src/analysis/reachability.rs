@@ -0,0 +1,266 @@
+use arrayvec::ArrayVec;
+
+use crate::game::{Board, Team, Vec2, Doubled, BOARD_FIELDS, TEAMS, PENGUINS_PER_TEAM};
+
+use super::{Confinement, FieldSet};
+
+/// The minimum number of moves needed to reach each field,
+/// computed by a breadth-first search over sliding moves on
+/// a fixed board (i.e. ignoring floes melting along the way).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Distances {
+    steps: [u8; BOARD_FIELDS],
+}
+
+impl Distances {
+    /// The step count of fields that cannot be reached.
+    pub const UNREACHABLE: u8 = u8::MAX;
+
+    /// Performs a multi-source BFS from the given fields, which
+    /// have distance 0. Does not allocate.
+    pub fn from_sources(board: &Board, sources: impl IntoIterator<Item=Vec2<Doubled>>) -> Self {
+        let mut steps = [Self::UNREACHABLE; BOARD_FIELDS];
+        let mut queue = [0u8; BOARD_FIELDS];
+        let mut head = 0;
+        let mut tail = 0;
+
+        for source in sources {
+            let i = Board::index_for(source);
+            if steps[i] == Self::UNREACHABLE {
+                steps[i] = 0;
+                queue[tail] = i as u8;
+                tail += 1;
+            }
+        }
+
+        // Every field is enqueued at most once, so the queue cannot overflow.
+        while head < tail {
+            let i = queue[head] as usize;
+            head += 1;
+            let next = steps[i] + 1;
+            for m in board.possible_moves_from(Board::coords_for(i)) {
+                let j = Board::index_for(m.to());
+                if steps[j] == Self::UNREACHABLE {
+                    steps[j] = next;
+                    queue[tail] = j as u8;
+                    tail += 1;
+                }
+            }
+        }
+
+        Self { steps }
+    }
+
+    /// Computes the distances from all penguins of the given team.
+    pub fn of_team(board: &Board, team: Team) -> Self {
+        Self::from_sources(board, board.penguins().filter(|&(_, t)| t == team).map(|(c, _)| c))
+    }
+
+    /// Computes the distances from a single penguin.
+    pub fn of_penguin(board: &Board, penguin: Vec2<Doubled>) -> Self {
+        Self::from_sources(board, [penguin])
+    }
+
+    /// The raw step count at the given field index.
+    #[inline]
+    pub fn steps_at(&self, index: usize) -> u8 { self.steps[index] }
+
+    /// The number of moves needed to reach the given field, if reachable.
+    pub fn get(&self, coords: impl Into<Vec2<Doubled>>) -> Option<u8> {
+        Some(self.steps[Board::index_for(coords)]).filter(|&s| s != Self::UNREACHABLE)
+    }
+
+    /// Iterates the reachable fields (excluding the sources) with their distances.
+    pub fn iter(&self) -> impl Iterator<Item=(Vec2<Doubled>, u8)> + '_ {
+        self.steps
+            .iter()
+            .enumerate()
+            .filter(|&(_, &s)| s != 0 && s != Self::UNREACHABLE)
+            .map(|(i, &s)| (Board::coords_for(i).into(), s))
+    }
+
+    /// The reachable fields, excluding the sources.
+    pub fn reachable(&self) -> FieldSet {
+        let mut set = FieldSet::EMPTY;
+        for (i, &s) in self.steps.iter().enumerate() {
+            if s != 0 && s != Self::UNREACHABLE {
+                set.insert_index(i);
+            }
+        }
+        set
+    }
+}
+
+/// The fields a penguin reaches strictly before any opposing penguin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Territory {
+    /// The position of the penguin.
+    pub penguin: Vec2<Doubled>,
+    /// The fields in the territory.
+    pub fields: FieldSet,
+    /// The total fish in the territory.
+    pub fish: usize,
+}
+
+/// A partition of the remaining floes by which team reaches them first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Voronoi {
+    /// The fields each team reaches strictly first.
+    pub owned: [FieldSet; TEAMS],
+    /// The fields both teams reach in the same number of moves.
+    pub contested: FieldSet,
+    /// The fish on the fields each team reaches strictly first.
+    pub fish: [usize; TEAMS],
+    /// The fish on the contested fields.
+    pub contested_fish: usize,
+}
+
+impl Voronoi {
+    /// The fields owned by the given team.
+    pub fn owned_by(&self, team: Team) -> FieldSet { self.owned[team.index()] }
+
+    /// The fish owned by the given team.
+    pub fn fish_of(&self, team: Team) -> usize { self.fish[team.index()] }
+}
+
+/// The distances of both teams on a board, the shared basis
+/// for reachability-based evaluation features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reachability {
+    distances: [Distances; TEAMS],
+}
+
+impl Reachability {
+    /// Computes the distances of both teams on the given board.
+    pub fn of(board: &Board) -> Self {
+        Self { distances: [Team::One, Team::Two].map(|t| Distances::of_team(board, t)) }
+    }
+
+    /// The distances from the given team's penguins.
+    pub fn distances(&self, team: Team) -> &Distances { &self.distances[team.index()] }
+
+    /// Partitions the fish on the board by the team that reaches them first.
+    pub fn voronoi(&self, board: &Board) -> Voronoi {
+        let mut voronoi = Voronoi::default();
+        let [one, two] = &self.distances;
+        for (i, field) in board.fields().map(|(_, f)| f).enumerate() {
+            let fish = field.fish();
+            if fish == 0 {
+                continue;
+            }
+            let (s1, s2) = (one.steps_at(i), two.steps_at(i));
+            if s1 < s2 {
+                voronoi.owned[0].insert_index(i);
+                voronoi.fish[0] += fish;
+            } else if s2 < s1 {
+                voronoi.owned[1].insert_index(i);
+                voronoi.fish[1] += fish;
+            } else if s1 != Distances::UNREACHABLE {
+                voronoi.contested.insert_index(i);
+                voronoi.contested_fish += fish;
+            }
+        }
+        voronoi
+    }
+
+    /// Computes the territory of each penguin of the given team, i.e.
+    /// the fields it reaches strictly before every opposing penguin.
+    pub fn territories(&self, board: &Board, team: Team) -> ArrayVec<Territory, PENGUINS_PER_TEAM> {
+        let rival = self.distances(team.opponent());
+        board.penguins()
+            .filter(|&(_, t)| t == team)
+            .map(|(penguin, _)| {
+                let own = Distances::of_penguin(board, penguin);
+                let mut fields = FieldSet::EMPTY;
+                for i in own.reachable().indices() {
+                    if own.steps_at(i) < rival.steps_at(i) {
+                        fields.insert_index(i);
+                    }
+                }
+                Territory { penguin, fields, fish: fields.fish(board) }
+            })
+            .take(PENGUINS_PER_TEAM)
+            .collect()
+    }
+
+    /// Counts the penguins per team that are trapped in a small region
+    /// an opposing penguin can reach too.
+    pub fn trapped_penguins(&self, board: &Board) -> [usize; TEAMS] {
+        let mut trapped = [0; TEAMS];
+        for (penguin, team) in board.penguins() {
+            let rival = self.distances(team.opponent()).reachable();
+            let confinement = Confinement::with_rival(board, penguin, team, rival);
+            if confinement.is_trapped() && confinement.shared {
+                trapped[team.index()] += 1;
+            }
+        }
+        trapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::game::{Board, Team, Vec2, Doubled, Direct};
+
+    use super::{Distances, Reachability};
+
+    fn board() -> Board {
+        indoc! {r#"
+            R1230000
+            00000000
+            00000000
+            00000000
+            00000000
+            00000000
+            00000000
+            000001B1
+        "#}.parse().unwrap()
+    }
+
+    #[test]
+    fn test_distances() {
+        let board = board();
+        let distances = Distances::of_team(&board, Team::One);
+        assert_eq!(distances.get(Vec2::<Direct>::new(0, 0)), Some(0));
+        assert_eq!(distances.get(Vec2::<Direct>::new(1, 0)), Some(1));
+        assert_eq!(distances.get(Vec2::<Direct>::new(3, 0)), Some(1));
+        assert_eq!(distances.get(Vec2::<Direct>::new(7, 7)), None);
+        assert_eq!(distances.reachable().len(), 3);
+        assert_eq!(distances.iter().count(), 3);
+    }
+
+    #[test]
+    fn test_distances_multiple_steps() {
+        // A zig-zag path that can only be walked one floe at a time
+        let board: Board = indoc! {r#"
+            R0000000
+            10000000
+            10000000
+            00000000
+            00000000
+            00000000
+            00000000
+            0000000B
+        "#}.parse().unwrap();
+        let distances = Distances::of_team(&board, Team::One);
+        assert_eq!(distances.get(Vec2::<Doubled>::new(1, 1)), Some(1));
+        assert_eq!(distances.get(Vec2::<Direct>::new(0, 2)), Some(2));
+    }
+
+    #[test]
+    fn test_voronoi_and_territories() {
+        let board = board();
+        let reachability = Reachability::of(&board);
+        let voronoi = reachability.voronoi(&board);
+        assert_eq!(voronoi.fish_of(Team::One), 6);
+        assert_eq!(voronoi.fish_of(Team::Two), 2);
+        assert!(voronoi.contested.is_empty());
+
+        let territories = reachability.territories(&board, Team::Two);
+        assert_eq!(territories.len(), 1);
+        assert_eq!(territories[0].penguin, Vec2::<Direct>::new(6, 7).into());
+        assert_eq!(territories[0].fish, 2);
+    }
+}
@@ -0,0 +1,289 @@
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use log::{info, warn, error};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::task;
+use crate::client::{GameClientDelegate, Reconnect, DEFAULT_MOVE_TIMEOUT, join_request, notify, panic_message, is_disconnect};
+use crate::game::Move;
+use crate::protocol::GameResult;
+use crate::session::{Session, SessionEvent};
+use crate::util::{Error, Result};
+
+/// A client like `GameClient` that runs on the tokio runtime, so that
+/// many games can be played in one process alongside other tasks.
+/// The delegate computes its moves on the blocking thread pool.
+pub struct AsyncGameClient<D> where D: GameClientDelegate + Send + 'static {
+    delegate: D,
+    reservation_code: Option<String>,
+    room_id: Option<String>,
+    games: Option<usize>,
+    move_timeout: Duration,
+    replay_dir: Option<PathBuf>,
+    reconnect: Option<Reconnect>,
+}
+
+impl<D> AsyncGameClient<D> where D: GameClientDelegate + Send + 'static {
+    /// Creates a new client using the specified delegate.
+    pub fn new(delegate: D, reservation_code: Option<String>) -> Self {
+        Self {
+            delegate,
+            reservation_code,
+            room_id: None,
+            games: Some(1),
+            move_timeout: DEFAULT_MOVE_TIMEOUT,
+            replay_dir: None,
+            reconnect: None,
+        }
+    }
+
+    /// Joins the room with the given id instead of an arbitrary
+    /// open game (unless a reservation code is given).
+    pub fn with_room_id(mut self, room_id: impl Into<String>) -> Self {
+        self.room_id = Some(room_id.into());
+        self
+    }
+
+    /// Plays the given number of games over the same connection, or keeps
+    /// playing until the server closes the connection if `None`.
+    pub fn with_games(mut self, games: Option<usize>) -> Self {
+        self.games = games;
+        self
+    }
+
+    /// Records a replay of each game into the given directory.
+    pub fn with_replay_dir(mut self, replay_dir: impl Into<PathBuf>) -> Self {
+        self.replay_dir = Some(replay_dir.into());
+        self
+    }
+
+    /// Sets the time after which a fallback move is sent.
+    pub fn with_move_timeout(mut self, move_timeout: Duration) -> Self {
+        self.move_timeout = move_timeout;
+        self
+    }
+
+    /// Reconnects and joins the reserved game again if the connection
+    /// drops mid-game, keeping the delegate and its state.
+    pub fn with_reconnect(mut self, reconnect: Reconnect) -> Self {
+        self.reconnect = Some(reconnect);
+        self
+    }
+
+    /// Connects to the given address via TCP and plays until all
+    /// games are finished. Returns the results of the games played.
+    pub async fn connect(self, host: &str, port: u16) -> Result<Vec<GameResult>> {
+        let address = format!("{}:{}", host, port);
+        let mut stream = TcpStream::connect(&address).await?;
+        info!("Connected to {}", address);
+
+        let (reconnect, move_timeout) = (self.reconnect, self.move_timeout);
+        let (mut session, mut delegate) = self.into_session();
+        loop {
+            let (read, write) = stream.split();
+            match (play(&mut session, &mut delegate, move_timeout, read, write).await, reconnect) {
+                (Err(e), Some(reconnect)) if is_disconnect(&e) && session.can_resume() => {
+                    warn!("Lost connection: {}", e);
+                    stream = reconnect_tcp(&address, &reconnect, e).await?;
+                    session.resume()?;
+                },
+                (result, _) => break result?,
+            }
+        }
+        session.into_results()
+    }
+
+    /// Plays over the given streams until all games are finished.
+    pub async fn run(self, read: impl AsyncRead + Unpin, write: impl AsyncWrite + Unpin) -> Result<Vec<GameResult>> {
+        let move_timeout = self.move_timeout;
+        let (mut session, mut delegate) = self.into_session();
+        play(&mut session, &mut delegate, move_timeout, read, write).await?;
+        session.into_results()
+    }
+
+    /// The session and the delegate, which is handed to the
+    /// blocking thread pool while it computes a move.
+    fn into_session(self) -> (Session, Option<D>) {
+        let session = Session::new(join_request(self.reservation_code, self.room_id)).with_games(self.games);
+        let session = match self.replay_dir {
+            Some(replay_dir) => session.with_replay_dir(replay_dir),
+            None => session,
+        };
+        (session, Some(self.delegate))
+    }
+}
+
+/// Drives the session over the given streams until it is finished.
+async fn play<D>(
+    session: &mut Session,
+    delegate: &mut Option<D>,
+    move_timeout: Duration,
+    mut read: impl AsyncRead + Unpin,
+    mut write: impl AsyncWrite + Unpin,
+) -> Result<()> where D: GameClientDelegate + Send + 'static {
+    let mut buf = vec![0; 8192];
+    loop {
+        let outgoing = session.take_outgoing();
+        if !outgoing.is_empty() {
+            write_all(&mut write, &outgoing).await?;
+        }
+        match session.poll_event()? {
+            Some(SessionEvent::MoveRequested(state)) => {
+                let start = Instant::now();
+                let mut computing = delegate.take().expect("Delegate is available between moves");
+                let mut handle = task::spawn_blocking(move || {
+                    let team = state.current_team();
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| computing.request_move(&state, team)));
+                    (computing, result)
+                });
+                let new_move: Option<Move> = match tokio::time::timeout(move_timeout, &mut handle).await {
+                    Ok(joined) => {
+                        let (computing, result) = joined.map_err(task_failed)?;
+                        *delegate = Some(computing);
+                        match result {
+                            Ok(m) => Some(m),
+                            Err(payload) => {
+                                error!("Delegate panicked: {}", panic_message(&payload));
+                                None
+                            },
+                        }
+                    },
+                    Err(_) => {
+                        // Answer in time, then wait for the delegate to use it for later moves
+                        error!("Delegate did not answer within {} ms", move_timeout.as_millis());
+                        let duration = start.elapsed();
+                        let sent = session.send_move(None, duration)?;
+                        let written = write_all(&mut write, &session.take_outgoing()).await;
+                        let (mut computing, _) = handle.await.map_err(task_failed)?;
+                        if let Some(sent) = sent {
+                            computing.on_move_sent(&state, sent, duration, true);
+                        }
+                        *delegate = Some(computing);
+                        written?;
+                        continue;
+                    },
+                };
+                let duration = start.elapsed();
+                if let Some(sent) = session.send_move(new_move, duration)? {
+                    let delegate = delegate.as_mut().expect("Delegate is available between moves");
+                    delegate.on_move_sent(&state, sent, duration, new_move != Some(sent));
+                }
+            },
+            Some(event) => notify(delegate.as_mut().expect("Delegate is available between moves"), event),
+            None if session.is_finished() => break,
+            None => match read.read(&mut buf).await? {
+                0 => session.close(),
+                n => session.receive(&buf[..n]),
+            },
+        }
+    }
+    write_all(&mut write, &session.take_outgoing()).await
+}
+
+async fn write_all(write: &mut (impl AsyncWrite + Unpin), bytes: &[u8]) -> Result<()> {
+    write.write_all(bytes).await?;
+    write.flush().await?;
+    Ok(())
+}
+
+/// Connects to the address again, waiting longer after each failed
+/// attempt. Fails with the error of the lost connection when giving up.
+async fn reconnect_tcp(address: &str, reconnect: &Reconnect, lost: Error) -> Result<TcpStream> {
+    for attempt in 0..reconnect.attempts {
+        let delay = reconnect.delay(attempt);
+        info!("Reconnecting to {} in {} ms", address, delay.as_millis());
+        tokio::time::sleep(delay).await;
+        match TcpStream::connect(address).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => warn!("Could not reconnect: {}", e),
+        }
+    }
+    Err(lost.context(format!("Could not reconnect within {} attempt(s)", reconnect.attempts)))
+}
+
+fn task_failed(e: task::JoinError) -> Error {
+    Error::from(io::Error::from(e)).context("Delegate task failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::client::GameClientDelegate;
+    use crate::game::{Move, State, Team};
+
+    use super::AsyncGameClient;
+
+    struct SlowDelegate;
+
+    impl GameClientDelegate for SlowDelegate {
+        fn request_move(&mut self, state: &State, _my_team: Team) -> Move {
+            std::thread::sleep(Duration::from_millis(200));
+            state.possible_moves()[0]
+        }
+    }
+
+    const STATE: &str = r#"
+        <state class="state" turn="0">
+            <startTeam>ONE</startTeam>
+            <board>
+                <list><field>1</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>1</field></list>
+            </board>
+            <fishes><int>0</int><int>0</int></fishes>
+        </state>
+    "#;
+
+    #[test]
+    fn test_run() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let (client_side, mut server_side) = tokio::io::duplex(1 << 16);
+            let (read, write) = tokio::io::split(client_side);
+            let client = AsyncGameClient::new(SlowDelegate, None).with_move_timeout(Duration::from_millis(50));
+            let game = tokio::spawn(client.run(read, write));
+
+            server_side.write_all(format!(r#"
+                <protocol>
+                    <joined roomId="a"/>
+                    <room roomId="a"><data class="memento">{}</data></room>
+                    <room roomId="a"><data class="moveRequest"/></room>
+            "#, STATE).as_bytes()).await.unwrap();
+
+            // The slow delegate is replaced by the fallback move, which picks
+            // the last of the equally good floes
+            let mut received = Vec::new();
+            while !String::from_utf8_lossy(&received).contains("</room>") {
+                let mut buf = [0; 1024];
+                let n = server_side.read(&mut buf).await.unwrap();
+                received.extend_from_slice(&buf[..n]);
+            }
+            let received = String::from_utf8(received).unwrap();
+            assert!(received.starts_with(r#"<protocol><join gameType="swc_2023_penguins"/>"#));
+            assert!(received.contains(r#"x="15""#) && received.contains(r#"y="7""#));
+
+            server_side.write_all(br#"
+                    <room roomId="a">
+                        <data class="result">
+                            <definition/>
+                            <scores/>
+                        </data>
+                    </room>
+                    <left roomId="a"/>
+                </protocol>
+            "#).await.unwrap();
+            let results = game.await.unwrap().unwrap();
+            assert_eq!(results.len(), 1);
+        });
+    }
+}
@@ -0,0 +1,117 @@
+use std::{process::{Child, Command}, str::FromStr};
+
+use clap::Parser;
+use log::{info, warn, error, LevelFilter};
+use simplelog::{SimpleLogger, Config};
+use socha_client_2023::{admin::AdminClient, protocol::{Event, EventPayload, Slot}, render::Renderer, replay::ReplayRecorder, util::Error};
+
+/// Prepares a match on a local server and observes it.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The game server's host address.
+    #[clap(short, long, default_value = "localhost")]
+    host: String,
+    /// The game server's port.
+    #[clap(short, long, default_value_t = 13050)]
+    port: u16,
+    /// The administrator password of the server.
+    #[clap(long, default_value = "examplepassword")]
+    password: String,
+    /// The display names of the two players.
+    #[clap(long, number_of_values = 2, default_values = &["One", "Two"])]
+    names: Vec<String>,
+    /// Commands to start the players with, each is called with
+    /// `--reservation <code>` appended. Can be given up to two times.
+    #[clap(long, max_occurrences = 2)]
+    player: Vec<String>,
+    /// A directory to record a replay of the game into.
+    #[clap(long)]
+    replay_dir: Option<String>,
+    /// Draws the board in the terminal after every move.
+    #[clap(long)]
+    render: bool,
+    /// The level to log at.
+    #[clap(short, long, default_value = "Info")]
+    level: String,
+}
+
+/// Starts a player with the given command line and reservation code.
+fn spawn_player(command: &str, reservation: &str) -> std::io::Result<Child> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+    Command::new(program).args(parts).arg("--reservation").arg(reservation).spawn()
+}
+
+fn main() {
+    let args = Args::parse();
+
+    SimpleLogger::init(LevelFilter::from_str(&args.level).expect("Invalid log level."), Config::default()).expect("Could not initialize logger.");
+
+    let mut client = AdminClient::connect(&args.host, args.port, &args.password).expect("Could not connect to server.");
+    let slots = args.names.iter().map(|n| Slot::new(n, true, true)).collect();
+    let (room_id, reservations) = client.prepare(slots, false).expect("Could not prepare game.");
+    info!("Prepared room {}", room_id);
+    for (name, reservation) in args.names.iter().zip(&reservations) {
+        println!("{}: --reservation {}", name, reservation);
+    }
+
+    client.observe(&room_id).expect("Could not observe game.");
+    let mut players: Vec<Child> = args.player.iter().zip(&reservations)
+        .map(|(command, reservation)| spawn_player(command, reservation).expect("Could not start player."))
+        .collect();
+
+    let mut recorder = args.replay_dir.as_ref().map(ReplayRecorder::new);
+    if let Some(recorder) = &mut recorder {
+        recorder.record_room(&room_id);
+    }
+    loop {
+        match client.next_event() {
+            Ok((Event::Room { payload: EventPayload::Memento(state), .. }, xml)) => {
+                info!("Turn {}: {}", state.turn(), state.last_move().map_or("-".to_owned(), |m| m.to_string()));
+                if args.render {
+                    print!("{}", Renderer::new().color(true).last_move(state.last_move()).render(state.board()));
+                }
+                if let Some(recorder) = &mut recorder {
+                    match xml.child_by_name("data").and_then(|d| d.child_by_name("state")) {
+                        Ok(state_xml) => recorder.record_state(state, state_xml),
+                        Err(e) => warn!("Could not record state: {}", e),
+                    }
+                }
+            },
+            Ok((Event::Room { payload: EventPayload::GameResult(result), .. }, xml)) => {
+                match result.winner() {
+                    Some(winner) => info!("Winner: {} ({})", winner.team(), winner.name().unwrap_or("unnamed")),
+                    None => info!("Draw"),
+                }
+                if let Some(recorder) = &mut recorder {
+                    match xml.child_by_name("data") {
+                        Ok(data) => recorder.record_result(result, data),
+                        Err(e) => warn!("Could not record result: {}", e),
+                    }
+                }
+                break;
+            },
+            Ok((Event::Left { .. }, _)) => break,
+            Ok((event, _)) => info!("Got {:?}", event),
+            Err(e @ Error::ServerError { .. }) => error!("{}", e),
+            Err(Error::UnknownElement(element)) => warn!("Got unknown tag <{}>: {}", element.name(), element),
+            Err(Error::Malformed { position, message } | Error::LimitExceeded { position, message }) => warn!("Skipping message at {}: {}", position, message),
+            Err(e) => {
+                error!("Error while observing: {}", e);
+                break;
+            },
+        }
+    }
+
+    if let Some(recorder) = &recorder {
+        match recorder.save() {
+            Ok(path) => info!("Saved replay to {}", path.display()),
+            Err(e) => error!("Could not save replay: {}", e),
+        }
+    }
+    for player in &mut players {
+        if let Err(e) = player.wait() {
+            warn!("Could not wait for player: {}", e);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::{fs, str::FromStr, time::{Duration, Instant}};
+
+use clap::Parser;
+use log::{info, warn, LevelFilter};
+use simplelog::{SimpleLogger, Config};
+use socha_client_2023::{game::{Move, State, Team}, minimax::{new_minimax, DECIDED}, patterns::{self, PatternTable}, replay::Replay};
+
+/// Re-searches every position of a recorded game and reports blunders.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The replay to analyze, either recorded by this client or in the official server format.
+    replay: String,
+    /// The search depth per position.
+    #[clap(long, default_value_t = 3)]
+    depth: i32,
+    /// Deepens the search iteratively while the given time per position is not used up.
+    #[clap(long)]
+    millis: Option<u64>,
+    /// The score drop above which a move counts as a blunder.
+    #[clap(short, long, default_value_t = 10.0)]
+    threshold: f32,
+    /// The team whose moves are ours (ONE or TWO), defaults to the team recorded in the replay.
+    #[clap(long)]
+    team: Option<String>,
+    /// Writes the eval curve as CSV to the given file.
+    #[clap(long)]
+    csv: Option<String>,
+    /// A learned pattern table to evaluate penguin neighborhoods with.
+    #[clap(long)]
+    patterns: Option<String>,
+    /// The level to log at.
+    #[clap(short, long, default_value = "Info")]
+    level: String,
+}
+
+/// The analysis of a single position.
+struct Position {
+    /// The position in state notation.
+    notation: String,
+    turn: usize,
+    team: Team,
+    played: Move,
+    best: Move,
+    depth: i32,
+    /// The score of the best move from the moving team's view.
+    best_score: f32,
+    /// The score of the played move from the moving team's view.
+    played_score: f32,
+}
+
+impl Position {
+    /// How much worse the played move is than the best one.
+    fn loss(&self) -> f32 { self.best_score - self.played_score }
+
+    /// The eval of the position from team one's view.
+    fn eval(&self) -> f32 {
+        match self.team {
+            Team::One => self.best_score,
+            Team::Two => -self.best_score,
+        }
+    }
+}
+
+/// Clamps the scores of decided games, so that
+/// they do not dominate the curve and the score drops.
+fn clamp(score: f32) -> f32 { score.clamp(-DECIDED, DECIDED) }
+
+/// Searches the given state at the given depth from the moving team's view.
+fn search(state: &State, depth: i32) -> (Option<Move>, f32) {
+    let team = state.current_team();
+    let (m, score) = new_minimax(&mut state.clone(), team, f32::MIN, f32::MAX, depth, depth - 1);
+    (m, clamp(score))
+}
+
+/// Scores the given move by searching the resulting state.
+fn score_move(state: &State, m: Move, depth: i32) -> f32 {
+    let team = state.current_team();
+    let (_, score) = new_minimax(&mut state.child(m), team, f32::MIN, f32::MAX, depth - 1, depth - 2);
+    clamp(score)
+}
+
+fn analyze(state: &State, played: Move, args: &Args) -> Option<Position> {
+    let start = Instant::now();
+    let budget = args.millis.map(Duration::from_millis);
+    let mut depth = args.depth.max(1);
+    let (mut best, mut best_score) = search(state, depth);
+    if let Some(budget) = budget {
+        while start.elapsed() < budget && depth < 30 {
+            depth += 1;
+            (best, best_score) = search(state, depth);
+        }
+    }
+    let best = best?;
+    let played_score = if played == best { best_score } else { score_move(state, played, depth) };
+    Some(Position {
+        notation: state.to_string(),
+        turn: state.turn(),
+        team: state.current_team(),
+        played,
+        best,
+        depth,
+        best_score,
+        played_score: played_score.min(best_score),
+    })
+}
+
+fn main() {
+    let args = Args::parse();
+
+    SimpleLogger::init(LevelFilter::from_str(&args.level).expect("Invalid log level."), Config::default()).expect("Could not initialize logger.");
+
+    if let Some(path) = &args.patterns {
+        let table = PatternTable::load(path).expect("Could not load pattern table.");
+        patterns::set_table(table).expect("Could not set pattern table.");
+    }
+
+    let replay = Replay::load(&args.replay).expect("Could not load replay.");
+    let our_team = args.team.as_ref().map(|t| t.parse::<Team>().expect("Invalid team.")).or(replay.team());
+    let states: Vec<&State> = replay.states().collect();
+    info!("Loaded {} states from {}", states.len(), args.replay);
+
+    // The move played in a state is the last move of its successor
+    let mut positions = Vec::new();
+    for pair in states.windows(2) {
+        let (state, next) = (pair[0], pair[1]);
+        let Some(played) = next.last_move().filter(|_| next.turn() == state.turn() + 1) else { continue };
+        if !state.is_legal(played) {
+            warn!("Skipping turn {}: {} is not legal there", state.turn(), played);
+            continue;
+        }
+        if let Some(position) = analyze(state, played, &args) {
+            positions.push(position);
+        }
+    }
+
+    println!("{:>4}  {:<4} {:<20} {:<20} {:>8} {:>8}", "turn", "team", "played", "best", "eval", "loss");
+    for p in &positions {
+        let ours = our_team.is_none_or(|t| t == p.team);
+        let marker = if ours && p.loss() > args.threshold { "??" } else { "" };
+        println!("{:>4}  {:<4} {:<20} {:<20} {:>8.2} {:>8.2} {}", p.turn, p.team, p.played.to_string(), p.best.to_string(), p.eval(), p.loss(), marker);
+    }
+
+    let blunders: Vec<&Position> = positions.iter()
+        .filter(|p| our_team.is_none_or(|t| t == p.team) && p.loss() > args.threshold)
+        .collect();
+    println!();
+    println!("{} blunder(s) with a score drop above {}", blunders.len(), args.threshold);
+    for p in blunders {
+        println!("  turn {}: played {}, best was {} ({:.2} worse)", p.turn, p.played, p.best, p.loss());
+        println!("    {}", p.notation);
+    }
+    if let Some(result) = replay.result() {
+        match result.winner() {
+            Some(winner) => println!("Winner: {} ({})", winner.team(), winner.name().unwrap_or("unnamed")),
+            None => println!("Draw"),
+        }
+    }
+
+    if let Some(path) = &args.csv {
+        let mut csv = String::from("turn,team,played,best,depth,eval,best_score,played_score,loss\n");
+        for p in &positions {
+            csv += &format!("{},{},\"{}\",\"{}\",{},{:.3},{:.3},{:.3},{:.3}\n", p.turn, p.team, p.played, p.best, p.depth, p.eval(), p.best_score, p.played_score, p.loss());
+        }
+        fs::write(path, csv).expect("Could not write CSV.");
+        info!("Wrote eval curve to {}", path);
+    }
+}
@@ -0,0 +1,56 @@
+use std::{fs, str::FromStr};
+
+use clap::Parser;
+use log::{info, warn, LevelFilter};
+use simplelog::{SimpleLogger, Config};
+use socha_client_2023::patterns::{PatternLearner, decode_sample};
+
+/// Learns a pattern table from recorded pattern samples.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The recorded samples, one position per line.
+    #[clap(required = true)]
+    samples: Vec<String>,
+    /// The file to write the learned table to.
+    #[clap(short, long, default_value = "patterns.txt")]
+    output: String,
+    /// Whether to include the second ring in the patterns.
+    #[clap(short, long)]
+    ring: bool,
+    /// The number of passes over the samples.
+    #[clap(short, long, default_value_t = 20)]
+    epochs: usize,
+    /// The learning rate.
+    #[clap(long, default_value_t = 0.01)]
+    rate: f32,
+    /// The level to log at.
+    #[clap(short, long, default_value = "Info")]
+    level: String,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    SimpleLogger::init(LevelFilter::from_str(&args.level).expect("Invalid log level."), Config::default()).expect("Could not initialize logger.");
+
+    let mut samples = Vec::new();
+    for path in &args.samples {
+        let contents = fs::read_to_string(path).expect("Could not read samples.");
+        for (i, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            match decode_sample(line) {
+                Ok(sample) => samples.push(sample),
+                Err(e) => warn!("Skipping {}:{}: {}", path, i + 1, e),
+            }
+        }
+    }
+    info!("Loaded {} samples", samples.len());
+
+    let mut learner = PatternLearner::new(args.ring, args.rate);
+    for epoch in 0..args.epochs {
+        let error: f32 = samples.iter().map(|(board, result)| learner.train(board, *result)).sum();
+        info!("Epoch {}: mean squared error {:.5}", epoch + 1, error / samples.len().max(1) as f32);
+    }
+
+    fs::write(&args.output, learner.table().to_string()).expect("Could not write pattern table.");
+    info!("Wrote pattern table to {}", args.output);
+}
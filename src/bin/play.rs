@@ -0,0 +1,173 @@
+use std::{io::{self, BufRead, Write}, str::FromStr};
+
+use clap::Parser;
+use log::LevelFilter;
+use rand::{SeedableRng, rngs::StdRng};
+use simplelog::{SimpleLogger, Config};
+use socha_client_2023::{game::{Board, Move, State, Team}, minimax::{new_minimax, DECIDED}, patterns::{self, PatternTable}, render::Renderer};
+
+/// Plays a game against the engine in the terminal.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The team to play as (ONE or TWO), team one starts.
+    #[clap(short, long, default_value = "ONE")]
+    team: String,
+    /// The search depth of the engine.
+    #[clap(long, default_value_t = 3)]
+    depth: i32,
+    /// The seed to generate the board with, random if not given.
+    #[clap(long)]
+    seed: Option<u64>,
+    /// A position in state notation to start from instead of a generated board.
+    #[clap(long)]
+    position: Option<String>,
+    /// Disables colored output.
+    #[clap(long)]
+    no_color: bool,
+    /// A learned pattern table to evaluate penguin neighborhoods with.
+    #[clap(long)]
+    patterns: Option<String>,
+    /// The level to log at.
+    #[clap(short, long, default_value = "Warn")]
+    level: String,
+}
+
+const HELP: &str = "Enter a move like d4-f4, d4:E2 or d4 (placement), or one of
+  moves   list the legal moves
+  eval    show the engine's evaluation and principal variation
+  state   print the position in state notation
+  board   show the board with legal targets and territories
+  undo    take back your last move
+  help    show this help
+  quit    end the game";
+
+/// A performed move along with what is needed to undo it.
+struct Ply {
+    m: Move,
+    fish: usize,
+    team: Team,
+}
+
+/// Searches the state and follows the best moves to get the principal variation,
+/// returning the score from the view of the team to move.
+fn principal_variation(state: &State, depth: i32) -> (f32, Vec<Move>) {
+    let team = state.current_team();
+    let (_, score) = new_minimax(&mut state.clone(), team, f32::MIN, f32::MAX, depth, depth - 1);
+    let mut line = Vec::new();
+    let mut current = *state;
+    for d in (1..=depth).rev() {
+        if current.is_over() {
+            break;
+        }
+        let Some(m) = new_minimax(&mut current.clone(), team, f32::MIN, f32::MAX, d, d - 1).0 else { break };
+        line.push(m);
+        current = current.child(m);
+    }
+    (score.clamp(-DECIDED, DECIDED), line)
+}
+
+fn describe_eval(state: &State, depth: i32) -> String {
+    let (score, line) = principal_variation(state, depth);
+    let line: Vec<_> = line.iter().map(|m| m.to_string()).collect();
+    format!("eval {:+.2} for {} (depth {}), pv: {}", score, state.current_team(), depth, line.join(" "))
+}
+
+fn perform(state: &mut State, history: &mut Vec<Ply>, m: Move) {
+    let team = state.current_team();
+    let fish = state.perform(m);
+    history.push(Ply { m, fish, team });
+}
+
+fn main() {
+    let args = Args::parse();
+
+    SimpleLogger::init(LevelFilter::from_str(&args.level).expect("Invalid log level."), Config::default()).expect("Could not initialize logger.");
+
+    if let Some(path) = &args.patterns {
+        let table = PatternTable::load(path).expect("Could not load pattern table.");
+        patterns::set_table(table).expect("Could not set pattern table.");
+    }
+
+    let human: Team = args.team.parse().expect("Invalid team.");
+    let mut state = match &args.position {
+        Some(position) => position.parse::<State>().expect("Invalid position."),
+        None => {
+            let mut rng = args.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+            State::new(Board::random(&mut rng), 0, [0, 0], None, Team::One)
+        },
+    };
+    let mut history: Vec<Ply> = Vec::new();
+    let color = !args.no_color;
+
+    println!("You play {} ({}), type 'help' for the commands.", human, human.letter());
+    let mut lines = io::stdin().lock().lines();
+    let mut redraw = true;
+    while !state.is_over() {
+        if redraw {
+            println!();
+            print!("{}", Renderer::new().color(color).last_move(history.last().map(|p| p.m)).render(state.board()));
+            println!("Turn {}, fish {}: {}, {}: {}", state.turn(), Team::One, state.fish(Team::One), Team::Two, state.fish(Team::Two));
+        }
+        redraw = true;
+
+        let team = state.current_team();
+        if team != human {
+            let Some(m) = new_minimax(&mut state.clone(), team, f32::MIN, f32::MAX, args.depth, args.depth - 1).0 else { break };
+            println!("Engine plays {}", m);
+            perform(&mut state, &mut history, m);
+            continue;
+        }
+
+        print!("{} to move> ", team);
+        io::stdout().flush().expect("Could not flush stdout.");
+        let Some(Ok(line)) = lines.next() else { break };
+        redraw = false;
+        match line.trim() {
+            "" => {},
+            "quit" | "exit" => return,
+            "help" => println!("{}", HELP),
+            "moves" => {
+                let moves: Vec<_> = state.possible_moves().iter().map(|m| m.to_string()).collect();
+                println!("{} legal moves: {}", moves.len(), moves.join(" "));
+            },
+            "eval" => println!("{}", describe_eval(&state, args.depth)),
+            "state" => println!("{}", state),
+            "board" => print!("{}", Renderer::new().color(color)
+                .targets(state.possible_moves().into_iter().map(|m| m.to()).collect())
+                .highlight_territory(state.board())
+                .render(state.board())),
+            "undo" => {
+                // Take back the engine's replies and the last own move
+                if history.iter().any(|p| p.team == human) {
+                    redraw = true;
+                    while let Some(ply) = history.pop() {
+                        state.undo_move(ply.m, ply.fish, ply.team);
+                        if ply.team == human {
+                            break;
+                        }
+                    }
+                } else {
+                    println!("Nothing to undo");
+                }
+            },
+            input => match input.parse::<Move>() {
+                Ok(m) => match state.check_move(m) {
+                    Ok(()) => {
+                        perform(&mut state, &mut history, m);
+                        redraw = true;
+                    },
+                    Err(e) => println!("Illegal move {}: {}", m, e),
+                },
+                Err(e) => println!("Could not parse '{}': {}", input, e),
+            },
+        }
+    }
+
+    println!();
+    print!("{}", Renderer::new().color(color).last_move(history.last().map(|p| p.m)).render(state.board()));
+    match state.winner() {
+        Some(winner) if winner == human => println!("You win with {} to {} fish!", state.fish(human), state.fish(human.opponent())),
+        Some(_) => println!("The engine wins with {} to {} fish.", state.fish(human.opponent()), state.fish(human)),
+        None => println!("Draw with {} fish each.", state.fish(human)),
+    }
+}
@@ -0,0 +1,111 @@
+use std::{fs, path::Path, process};
+
+use clap::Parser;
+use log::{info, LevelFilter};
+use simplelog::{SimpleLogger, Config};
+use socha_client_2023::{analysis::Distances, game::{State, Team}, render::Renderer, replay::Replay, svg::SvgRenderer, util::Element};
+
+/// Renders positions from state notation or replay files as SVG images.
+/// Other image formats are out of scope; convert the images with a tool
+/// like rsvg-convert if needed.
+#[derive(Parser, Debug)]
+struct Args {
+    /// A position in state notation or the path of a replay.
+    input: String,
+    /// The file to write a single position to, or the directory to write
+    /// all positions of a replay to.
+    #[clap(short, long)]
+    output: Option<String>,
+    /// Only renders the position of the given turn from a replay.
+    #[clap(short, long)]
+    turn: Option<usize>,
+    /// Overlays the number of moves the given team (ONE or TWO) needs to reach each field.
+    #[clap(long)]
+    heatmap: Option<Team>,
+    /// Draws the name of each field.
+    #[clap(long)]
+    labels: bool,
+    /// The radius of a hexagon in pixels.
+    #[clap(long, default_value_t = 30.0)]
+    radius: f32,
+    /// Prints the positions as text instead of writing images.
+    #[clap(long)]
+    text: bool,
+    /// Prints the positions in the server's XML format instead of writing images.
+    #[clap(long, conflicts_with = "text")]
+    xml: bool,
+    /// The level to log at.
+    #[clap(short, long, default_value = "Info")]
+    level: LevelFilter,
+}
+
+fn render_svg(state: &State, args: &Args, heatmap: Option<Team>) -> String {
+    let mut renderer = SvgRenderer::new().radius(args.radius).labels(args.labels);
+    if let Some(m) = state.last_move() {
+        renderer = renderer.arrow(m);
+    }
+    if let Some(team) = heatmap {
+        renderer = renderer.distances(&Distances::of_team(state.board(), team));
+    }
+    renderer.render(state)
+}
+
+/// Prints the error and exits with a failure status.
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let args = Args::parse();
+
+    SimpleLogger::init(args.level, Config::default()).expect("Could not initialize logger.");
+
+    let heatmap = args.heatmap;
+    let (states, single) = match args.input.parse::<State>() {
+        Ok(state) => (vec![state], true),
+        Err(e) => {
+            let replay = Replay::load(&args.input).unwrap_or_else(|replay_error| fail(format_args!(
+                "{} is neither a position ({}) nor a readable replay ({})", args.input, e, replay_error,
+            )));
+            let states: Vec<State> = replay.states().filter(|s| args.turn.is_none_or(|t| s.turn() == t)).copied().collect();
+            let single = args.turn.is_some();
+            (states, single)
+        },
+    };
+    if states.is_empty() {
+        match args.turn {
+            Some(turn) => fail(format_args!("The replay has no position at turn {}", turn)),
+            None => fail("The replay has no positions"),
+        }
+    }
+
+    if args.text {
+        for state in &states {
+            println!("{}", state);
+            print!("{}", Renderer::new().color(true).last_move(state.last_move()).render(state.board()));
+        }
+        return;
+    }
+
+    if args.xml {
+        for state in &states {
+            println!("{}", Element::from(*state));
+        }
+        return;
+    }
+
+    if single {
+        let path = args.output.clone().unwrap_or_else(|| "board.svg".to_owned());
+        fs::write(&path, render_svg(&states[0], &args, heatmap)).unwrap_or_else(|e| fail(format_args!("Could not write {}: {}", path, e)));
+        info!("Wrote {}", path);
+    } else {
+        let directory = args.output.clone().unwrap_or_else(|| "renders".to_owned());
+        fs::create_dir_all(&directory).unwrap_or_else(|e| fail(format_args!("Could not create {}: {}", directory, e)));
+        for state in &states {
+            let path = Path::new(&directory).join(format!("turn-{:02}.svg", state.turn()));
+            fs::write(&path, render_svg(state, &args, heatmap)).unwrap_or_else(|e| fail(format_args!("Could not write {}: {}", path.display(), e)));
+        }
+        info!("Wrote {} images to {}", states.len(), directory);
+    }
+}
@@ -0,0 +1,57 @@
+use std::{fs::File, io::{self, BufReader}, process};
+
+use clap::Parser;
+use socha_client_2023::telemetry::{Record, Summary};
+
+/// Aggregates the telemetry logs written by the client with --telemetry.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The JSON Lines logs to read, standard input if none are given.
+    files: Vec<String>,
+    /// Summarizes each strategy on its own.
+    #[clap(short, long)]
+    by_strategy: bool,
+    /// Lists moves that took longer than the given milliseconds.
+    #[clap(long)]
+    slower_than: Option<f64>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut records = Vec::new();
+    if args.files.is_empty() {
+        records = Record::read_all(io::stdin().lock()).unwrap_or_else(|e| {
+            eprintln!("Could not read standard input: {}", e);
+            process::exit(1);
+        });
+    }
+    for path in &args.files {
+        let file = File::open(path).unwrap_or_else(|e| {
+            eprintln!("Could not open {}: {}", path, e);
+            process::exit(1);
+        });
+        records.extend(Record::read_all(BufReader::new(file)).unwrap_or_else(|e| {
+            eprintln!("Could not read {}: {}", path, e);
+            process::exit(1);
+        }));
+    }
+
+    if args.by_strategy {
+        for (strategy, summary) in Summary::by_strategy(&records) {
+            println!("{}: {}", strategy, summary);
+        }
+    } else {
+        println!("{}", Summary::of(&records));
+    }
+
+    if let Some(limit) = args.slower_than {
+        for record in &records {
+            if let Record::Move(m) = record {
+                if m.time_ms > limit {
+                    println!("game {} turn {:>2} {:<10} {:>6.0} ms  {}", m.game, m.turn, m.chosen, m.time_ms, m.strategy.as_deref().unwrap_or("unknown"));
+                }
+            }
+        }
+    }
+}
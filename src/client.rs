@@ -1,11 +1,19 @@
+use std::any::Any;
 use std::net::TcpStream;
-use std::io::{self, BufWriter, BufReader, Read, Write};
-use log::{info, warn, debug, error};
-use quick_xml::events::{Event as XmlEvent, BytesStart};
-use quick_xml::{Reader, Writer};
+use std::io::{self, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::thread;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use log::{info, warn, error};
 use crate::game::{State, Team, Move};
-use crate::protocol::{Request, Event, GameResult, EventPayload, RequestPayload};
-use crate::util::{Result, Element, Error};
+use crate::protocol::{Request, GameResult};
+use crate::session::{Session, SessionEvent};
+use crate::telemetry::SearchInfo;
+use crate::util::{Error, Result};
+
+pub use crate::session::fallback_move;
 
 /// A handler that implements the game player's
 /// behavior, usually employing some custom move
@@ -14,6 +22,11 @@ pub trait GameClientDelegate {
     /// Invoked whenever the game state updates.
     fn on_update_state(&mut self, _state: &State) {}
     
+    /// Invoked when a new game is joined, before any other
+    /// callback of that game. Delegates that are reused across
+    /// games should reset their per-game state here.
+    fn on_game_start(&mut self) {}
+
     /// Invoked when the game ends.
     fn on_game_end(&mut self, _result: &GameResult) {}
     
@@ -24,6 +37,31 @@ pub trait GameClientDelegate {
     /// Requests a move from the delegate. This method
     /// should implement the "main" game logic.
     fn request_move(&mut self, state: &State, my_team: Team) -> Move;
+
+    /// Invoked after a move was sent in the given state, along with the
+    /// time it took. `fallback` tells whether the sent move replaced the
+    /// delegate's, which panicked, overran or picked an illegal move.
+    fn on_move_sent(&mut self, _state: &State, _m: Move, _duration: Duration, _fallback: bool) {}
+
+    /// Details about the search for the last requested
+    /// move, if the delegate searched for it.
+    fn search_info(&self) -> Option<SearchInfo> { None }
+}
+
+impl<D> GameClientDelegate for Box<D> where D: GameClientDelegate + ?Sized {
+    fn on_update_state(&mut self, state: &State) { (**self).on_update_state(state) }
+
+    fn on_game_start(&mut self) { (**self).on_game_start() }
+
+    fn on_game_end(&mut self, result: &GameResult) { (**self).on_game_end(result) }
+
+    fn on_welcome(&mut self, team: Team) { (**self).on_welcome(team) }
+
+    fn request_move(&mut self, state: &State, my_team: Team) -> Move { (**self).request_move(state, my_team) }
+
+    fn on_move_sent(&mut self, state: &State, m: Move, duration: Duration, fallback: bool) { (**self).on_move_sent(state, m, duration, fallback) }
+
+    fn search_info(&self) -> Option<SearchInfo> { (**self).search_info() }
 }
 
 /// A configuration that determines whether
@@ -37,21 +75,120 @@ pub struct DebugMode {
 /// The client which handles XML requests, manages
 /// the game state and invokes the delegate.
 pub struct GameClient<D> where D: GameClientDelegate {
-    delegate: D,
+    worker: Worker<D>,
     debug_mode: DebugMode,
     reservation_code: Option<String>,
+    room_id: Option<String>,
+    games: Option<usize>,
+    move_timeout: Duration,
+    replay_dir: Option<PathBuf>,
+    reconnect: Option<Reconnect>,
     // TODO: Add game state
 }
 
-impl<D> GameClient<D> where D: GameClientDelegate {
+/// How to reconnect when the connection drops during
+/// a game that was joined with a reservation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reconnect {
+    /// The number of attempts before giving up.
+    pub attempts: u32,
+    /// The delay before the first attempt, doubled after each failed one.
+    pub initial_delay: Duration,
+    /// The upper bound of the delay.
+    pub max_delay: Duration,
+}
+
+impl Reconnect {
+    /// The delay before the given attempt, counting from zero.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay)
+    }
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Whether the error means that the connection to the server was lost.
+pub(crate) fn is_disconnect(error: &Error) -> bool {
+    matches!(error, Error::Io(_) | Error::Eof)
+}
+
+/// The time after which a fallback move is sent if the
+/// delegate has not answered yet (the server allows 2 s).
+pub const DEFAULT_MOVE_TIMEOUT: Duration = Duration::from_millis(1900);
+
+/// Extracts the message of a panic payload.
+pub(crate) fn panic_message(payload: &Box<dyn Any + Send>) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "unknown panic"
+    }
+}
+
+impl<D> GameClient<D> where D: GameClientDelegate + Send + 'static {
     /// Creates a new client using the specified delegate.
     pub fn new(delegate: D, debug_mode: DebugMode, reservation_code: Option<String>) -> Self {
-        Self { delegate, debug_mode, reservation_code }
+        Self {
+            worker: Worker::new(delegate),
+            debug_mode,
+            reservation_code,
+            room_id: None,
+            games: Some(1),
+            move_timeout: DEFAULT_MOVE_TIMEOUT,
+            replay_dir: None,
+            reconnect: None,
+        }
+    }
+
+    /// Joins the room with the given id instead of an arbitrary
+    /// open game (unless a reservation code is given).
+    pub fn with_room_id(mut self, room_id: impl Into<String>) -> Self {
+        self.room_id = Some(room_id.into());
+        self
+    }
+
+    /// Plays the given number of games over the same connection, or keeps
+    /// playing until the server closes the connection if `None`. After the
+    /// first game, arbitrary open games are joined.
+    pub fn with_games(mut self, games: Option<usize>) -> Self {
+        self.games = games;
+        self
+    }
+
+    /// Records a replay of each game into the given directory.
+    pub fn with_replay_dir(mut self, replay_dir: impl Into<PathBuf>) -> Self {
+        self.replay_dir = Some(replay_dir.into());
+        self
+    }
+
+    /// Sets the time after which a fallback move is sent.
+    pub fn with_move_timeout(mut self, move_timeout: Duration) -> Self {
+        self.move_timeout = move_timeout;
+        self
+    }
+
+    /// Reconnects and joins the reserved game again if the connection
+    /// drops mid-game, keeping the delegate and its state.
+    pub fn with_reconnect(mut self, reconnect: Reconnect) -> Self {
+        self.reconnect = Some(reconnect);
+        self
     }
     
     /// Blocks the thread and begins reading XML messages
-    /// from the provided address via TCP.
-    pub fn connect(self, host: &str, port: u16) -> Result<GameResult> {
+    /// from the provided address via TCP. Returns the results
+    /// of the games played. Reconnecting is only supported
+    /// without debug mode.
+    pub fn connect(self, host: &str, port: u16) -> Result<Vec<GameResult>> {
         let address = format!("{}:{}", host, port);
         let stream = TcpStream::connect(&address)?;
         info!("Connected to {}", address);
@@ -62,103 +199,494 @@ impl<D> GameClient<D> where D: GameClientDelegate {
         // of `run_game`.
 
         let mode = &self.debug_mode;
-        let game_result = if mode.debug_reader && !mode.debug_writer {
+        let game_results = if mode.debug_reader && !mode.debug_writer {
             self.run(io::stdin(), stream)?
         } else if !mode.debug_reader && mode.debug_writer {
             self.run(stream, io::stdout())?
         } else if mode.debug_reader && mode.debug_writer {
             self.run(io::stdin(), io::stdout())?
         } else {
-            self.run(stream.try_clone()?, stream)?
+            self.run_tcp(&address, stream)?
         };
         
-        Ok(game_result)
+        Ok(game_results)
+    }
+
+    /// Plays over the given connection, reconnecting if it drops.
+    fn run_tcp(mut self, address: &str, mut stream: TcpStream) -> Result<Vec<GameResult>> {
+        let mut session = self.session();
+        loop {
+            match (self.play(&mut session, stream.try_clone()?, &stream), self.reconnect) {
+                (Err(e), Some(reconnect)) if is_disconnect(&e) && session.can_resume() => {
+                    warn!("Lost connection: {}", e);
+                    stream = reconnect_tcp(address, &reconnect, e)?;
+                    session.resume()?;
+                },
+                (result, _) => break result?,
+            }
+        }
+        session.into_results()
     }
     
     /// Blocks the thread and parses/handles game messages
     /// from the provided reader.
-    fn run(mut self, read: impl Read, write: impl Write) -> Result<GameResult> {
-        let mut buf = Vec::new();
-        let mut reader = Reader::from_reader(BufReader::new(read));
-        let mut writer = Writer::new(BufWriter::new(write));
+    fn run(mut self, read: impl Read, write: impl Write) -> Result<Vec<GameResult>> {
+        let mut session = self.session();
+        self.play(&mut session, read, write)?;
+        session.into_results()
+    }
 
-        // Write <protocol>
-        writer.write_event(XmlEvent::Start(BytesStart::borrowed_name(b"protocol")))?;
-        
-        // Send join request
-        let join_xml: Element = match self.reservation_code {
-            Some(code) => Request::JoinPrepared { reservation_code: code.to_owned() },
-            None => Request::Join,
-        }.into();
-        info!("Sending join request {}", &join_xml);
-        join_xml.write_to(&mut writer)?;
-
-        // Read <protocol>
+    fn session(&mut self) -> Session {
+        let join = join_request(self.reservation_code.take(), self.room_id.take());
+        let session = Session::new(join).with_games(self.games);
+        match self.replay_dir.take() {
+            Some(replay_dir) => session.with_replay_dir(replay_dir),
+            None => session,
+        }
+    }
+
+    /// Drives the session over the given streams until it is finished.
+    fn play(&mut self, session: &mut Session, mut read: impl Read, mut write: impl Write) -> Result<()> {
+        let mut buf = [0; 8192];
         loop {
-            match reader.read_event(&mut buf)? {
-                XmlEvent::Start(ref start) if start.name() == b"protocol" => {
-                    info!("Performed handshake");
-                    break
+            write.write_all(&session.take_outgoing())?;
+            write.flush()?;
+            match session.poll_event()? {
+                Some(SessionEvent::MoveRequested(state)) => {
+                    self.worker.play_move(session, &state, self.move_timeout)?;
+                },
+                Some(event) => self.worker.notify(event),
+                None if session.is_finished() => break,
+                None => match read.read(&mut buf) {
+                    Ok(0) => session.close(),
+                    Ok(n) => session.receive(&buf[..n]),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                    Err(e) => return Err(e.into()),
                 },
-                XmlEvent::Text(_) => (),
-                XmlEvent::Eof => return Err(Error::Eof),
-                e => warn!("Got unexpected event {:?}", e),
             }
         }
+        write.write_all(&session.take_outgoing())?;
+        write.flush()?;
+        Ok(())
+    }
+}
 
-        // Handle events from the server
-        let mut state: Option<State> = None;
-        let mut game_result: Option<GameResult> = None;
-        loop {
-            let event_xml = Element::read_from(&mut reader)?;
+/// Connects to the address again, waiting longer after each failed
+/// attempt. Fails with the error of the lost connection when giving up.
+fn reconnect_tcp(address: &str, reconnect: &Reconnect, lost: Error) -> Result<TcpStream> {
+    for attempt in 0..reconnect.attempts {
+        let delay = reconnect.delay(attempt);
+        info!("Reconnecting to {} in {} ms", address, delay.as_millis());
+        thread::sleep(delay);
+        match TcpStream::connect(address) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => warn!("Could not reconnect: {}", e),
+        }
+    }
+    Err(lost.context(format!("Could not reconnect within {} attempt(s)", reconnect.attempts)))
+}
 
-            debug!("Got event {}", event_xml);
-            match Event::try_from(&event_xml) {
-                Ok(Event::Joined { room_id }) => {
-                    info!("Joined room {}", room_id);
-                },
-                Ok(Event::Left { room_id }) => {
-                    info!("Left room {}", room_id);
-                    break;
-                },
-                Ok(Event::Room { room_id, payload }) => {
-                    info!("Got {} in room {}", payload, room_id);
-                    match payload {
-                        EventPayload::Welcome(team) => self.delegate.on_welcome(team),
-                        EventPayload::GameResult(result) => {
-                            self.delegate.on_game_end(&result);
-                            game_result = Some(result);
-                        },
-                        EventPayload::Memento(new_state) => {
-                            self.delegate.on_update_state(&new_state);
-                            state = Some(new_state);
-                        },
-                        EventPayload::MoveRequest => {
-                            let state = state.as_ref().ok_or_else(|| Error::InvalidState("No state available at move request!".to_owned()))?;
-                            let team = state.current_team();
-                            let new_move = self.delegate.request_move(state, team);
-                            let request = Request::Room { room_id, payload: RequestPayload::Move(new_move) };
-                            let request_xml = Element::from(request);
-                            request_xml.write_to(&mut writer)?;
-                        },
-                    };
-                },
-                Err(Error::UnknownElement(element)) => {
-                    warn!("Got unknown tag <{}>: {}", element.name(), element);
-                },
-                Err(Error::ServerError(message)) => {
-                    error!("Server error: {}", message);
-                },
-                Err(e) => {
-                    warn!("Error while parsing event: {:?}", e);
-                },
+/// The first request of a connection, joining the prepared game if a
+/// reservation code is given, the given room or an arbitrary open game.
+pub(crate) fn join_request(reservation_code: Option<String>, room_id: Option<String>) -> Request {
+    match (reservation_code, room_id) {
+        (Some(code), _) => Request::JoinPrepared { reservation_code: code },
+        (None, Some(room_id)) => Request::JoinRoom { room_id },
+        (None, None) => Request::Join,
+    }
+}
+
+/// Passes a session event other than a move request to the delegate.
+pub(crate) fn notify(delegate: &mut impl GameClientDelegate, event: SessionEvent) {
+    match event {
+        SessionEvent::GameStarted { .. } => delegate.on_game_start(),
+        SessionEvent::Welcome(team) => delegate.on_welcome(team),
+        SessionEvent::StateUpdated(state) => delegate.on_update_state(&state),
+        SessionEvent::GameEnded(result) => delegate.on_game_end(&result),
+        SessionEvent::Left { replay: Some(recorder), .. } => match recorder.save() {
+            Ok(path) => info!("Saved replay to {}", path.display()),
+            Err(e) => error!("Could not save replay: {}", e),
+        },
+        SessionEvent::Left { .. } | SessionEvent::MoveRequested(_) => (),
+    }
+}
+
+/// The delegate along with the thread it computes a move on. If it
+/// overruns, it keeps computing while the client answers later move
+/// requests with fallback moves, and the callbacks it misses are
+/// delivered once it is handed back, so that a stuck delegate can
+/// never block the client.
+struct Worker<D> {
+    delegate: Option<D>,
+    computing: Option<mpsc::Receiver<(D, thread::Result<Move>)>>,
+    deferred: Vec<Deferred>,
+}
+
+/// A callback that was due while the delegate was computing.
+enum Deferred {
+    Event(SessionEvent),
+    MoveSent { state: State, m: Move, duration: Duration, fallback: bool },
+}
+
+impl<D> Worker<D> where D: GameClientDelegate + Send + 'static {
+    fn new(delegate: D) -> Self {
+        Self { delegate: Some(delegate), computing: None, deferred: Vec::new() }
+    }
+
+    /// Takes the delegate back if it has finished computing,
+    /// passing it the callbacks it missed in the meantime.
+    fn reclaim(&mut self) {
+        let Some(Ok((mut delegate, result))) = self.computing.as_ref().map(mpsc::Receiver::try_recv) else { return };
+        self.computing = None;
+        match result {
+            Ok(_) => warn!("Delegate answered too late, its move was discarded"),
+            Err(payload) => error!("Delegate panicked: {}", panic_message(&payload)),
+        }
+        for deferred in self.deferred.drain(..) {
+            match deferred {
+                Deferred::Event(event) => notify(&mut delegate, event),
+                Deferred::MoveSent { state, m, duration, fallback } => delegate.on_move_sent(&state, m, duration, fallback),
+            }
+        }
+        self.delegate = Some(delegate);
+    }
+
+    /// Passes a session event other than a move request to the
+    /// delegate, or defers it while the delegate is computing.
+    fn notify(&mut self, event: SessionEvent) {
+        self.reclaim();
+        match &mut self.delegate {
+            Some(delegate) => notify(delegate, event),
+            None => self.deferred.push(Deferred::Event(event)),
+        }
+    }
+
+    /// Runs the delegate on its own thread and sends its move, so that
+    /// a panic or an overrun cannot forfeit the game. The fallback move
+    /// is sent if the deadline passes or the delegate is still computing
+    /// an earlier move.
+    fn play_move(&mut self, session: &mut Session, state: &State, timeout: Duration) -> Result<()> {
+        let start = Instant::now();
+        self.reclaim();
+        let chosen = match self.delegate.take() {
+            Some(mut delegate) => {
+                let (tx, rx) = mpsc::channel();
+                let requested = *state;
+                thread::spawn(move || {
+                    let team = requested.current_team();
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| delegate.request_move(&requested, team)));
+                    let _ = tx.send((delegate, result));
+                });
+                match rx.recv_timeout(timeout) {
+                    Ok((delegate, result)) => {
+                        self.delegate = Some(delegate);
+                        result.map_err(|payload| error!("Delegate panicked: {}", panic_message(&payload))).ok()
+                    },
+                    Err(_) => {
+                        error!("Delegate did not answer within {} ms", timeout.as_millis());
+                        self.computing = Some(rx);
+                        None
+                    },
+                }
+            },
+            None => {
+                error!("Delegate is still computing an earlier move");
+                None
+            },
+        };
+        let duration = start.elapsed();
+        if let Some(m) = session.send_move(chosen, duration)? {
+            let fallback = chosen != Some(m);
+            match &mut self.delegate {
+                Some(delegate) => delegate.on_move_sent(state, m, duration, fallback),
+                None => self.deferred.push(Deferred::MoveSent { state: *state, m, duration, fallback }),
             }
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::{Cursor, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicUsize, Ordering}};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::game::{Move, State, Team};
+    use crate::protocol::{Event, EventPayload, GameResult, ScoreDefinition};
+    use crate::util::Element;
+
+    use super::{DebugMode, GameClient, GameClientDelegate, Reconnect};
+
+    struct CountingDelegate {
+        games: Arc<AtomicUsize>,
+    }
+
+    impl GameClientDelegate for CountingDelegate {
+        fn on_game_start(&mut self) {
+            self.games.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn request_move(&mut self, state: &State, _my_team: Team) -> Move {
+            state.possible_moves()[0]
+        }
+    }
+
+    fn game(room_id: &str) -> String {
+        format!(r#"
+            <joined roomId="{room_id}"/>
+            <room roomId="{room_id}"><data class="welcomeMessage" color="ONE"/></room>
+            <room roomId="{room_id}">
+                <data class="result">
+                    <definition>
+                        <fragment name="Siegpunkte">
+                            <aggregation>SUM</aggregation>
+                            <relevantForRanking>true</relevantForRanking>
+                        </fragment>
+                    </definition>
+                    <scores>
+                        <entry>
+                            <player name="rad" team="ONE"/>
+                            <score cause="REGULAR" reason=""><part>2</part></score>
+                        </entry>
+                    </scores>
+                    <winner team="ONE"/>
+                </data>
+            </room>
+            <left roomId="{room_id}"/>
+        "#)
+    }
+
+    fn run(games: Option<usize>, input: String) -> (usize, String) {
+        let mut output = Vec::new();
+        let debug_mode = DebugMode { debug_reader: false, debug_writer: false };
+        let started = Arc::new(AtomicUsize::new(0));
+        let client = GameClient::new(CountingDelegate { games: started.clone() }, debug_mode, None)
+            .with_room_id("a")
+            .with_games(games);
+        let results = client.run(Cursor::new(input), &mut output).unwrap();
+        assert_eq!(started.load(Ordering::SeqCst), results.len());
+        (results.len(), String::from_utf8(output).unwrap())
+    }
 
-        if let Some(result) = game_result {
-            Ok(result)
-        }else {
-            Err(Error::InvalidState("Failed to receive game_result".to_string()))
+    #[test]
+    fn test_multiple_games() {
+        let input = format!("<protocol>{}{}{}", game("a"), game("b"), game("c"));
+
+        let (played, output) = run(Some(2), input.clone());
+        assert_eq!(played, 2);
+        assert!(output.contains(r#"<joinRoom roomId="a"/>"#));
+        assert_eq!(output.matches("<join ").count(), 1);
+
+        let (played, output) = run(Some(1), input.clone());
+        assert_eq!(played, 1);
+        assert_eq!(output.matches("<join ").count(), 0);
+
+        // Without a limit, games are played until the connection closes
+        let (played, output) = run(None, input);
+        assert_eq!(played, 3);
+        assert_eq!(output.matches("<join ").count(), 3);
+    }
+
+    #[test]
+    fn test_malformed_message() {
+        let input = format!("<protocol><joined><room></joined>{}", game("a"));
+        let (played, _) = run(Some(1), input);
+        assert_eq!(played, 1);
+    }
+
+    /// Reads from the stream until the received text contains the pattern.
+    fn read_until(stream: &mut TcpStream, pattern: &str) -> String {
+        let mut received = Vec::new();
+        while !String::from_utf8_lossy(&received).contains(pattern) {
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).unwrap();
+            assert!(n > 0, "Connection closed before receiving {}", pattern);
+            received.extend_from_slice(&buf[..n]);
         }
+        String::from_utf8(received).unwrap()
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let state: State = "10000000/00000000/00000000/00000000/00000000/00000000/00000000/00000001 R 0 0:0 -".parse().unwrap();
+            let event = |event: Event| Element::from(event).to_string();
+            let room = |payload| event(Event::Room { room_id: "a".to_owned(), payload });
+            let joined = event(Event::Joined { room_id: "a".to_owned() });
+
+            // The first connection drops after the first state
+            let (mut stream, _) = listener.accept().unwrap();
+            read_until(&mut stream, r#"<joinPrepared reservationCode="abc"/>"#);
+            write!(stream, "<protocol>{}{}", joined, room(EventPayload::Memento(state))).unwrap();
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().unwrap();
+            read_until(&mut stream, r#"<joinPrepared reservationCode="abc"/>"#);
+            write!(stream, "<protocol>{}{}{}", joined, room(EventPayload::Memento(state)), room(EventPayload::MoveRequest)).unwrap();
+            read_until(&mut stream, "</room>");
+            write!(stream, "{}{}</protocol>", room(EventPayload::GameResult(GameResult::new(ScoreDefinition::new([]), HashMap::new(), None))), event(Event::Left { room_id: "a".to_owned() })).unwrap();
+        });
+
+        let started = Arc::new(AtomicUsize::new(0));
+        let debug_mode = DebugMode { debug_reader: false, debug_writer: false };
+        let reconnect = Reconnect { attempts: 3, initial_delay: Duration::from_millis(10), max_delay: Duration::from_millis(100) };
+        let client = GameClient::new(CountingDelegate { games: started.clone() }, debug_mode, Some("abc".to_owned()))
+            .with_reconnect(reconnect);
+        let results = client.connect("127.0.0.1", port).unwrap();
+        server.join().unwrap();
+
+        // The delegate is kept and the resumed game is not started again
+        assert_eq!(results.len(), 1);
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+    }
+
+    /// How the delegate fails on its first move.
+    #[derive(Clone, Copy)]
+    enum Failure {
+        Panic,
+        Hang,
+    }
+
+    /// Fails on its first move and picks the first legal move afterwards.
+    struct FlakyDelegate {
+        failure: Failure,
+        calls: usize,
+        /// Whether each sent move was a fallback.
+        fallbacks: Arc<Mutex<Vec<bool>>>,
+    }
+
+    impl GameClientDelegate for FlakyDelegate {
+        fn request_move(&mut self, state: &State, _my_team: Team) -> Move {
+            self.calls += 1;
+            if self.calls == 1 {
+                match self.failure {
+                    Failure::Panic => panic!("Flaky delegate failed"),
+                    Failure::Hang => loop { thread::park() },
+                }
+            }
+            state.possible_moves()[0]
+        }
+
+        fn on_move_sent(&mut self, _state: &State, _m: Move, _duration: Duration, fallback: bool) {
+            self.fallbacks.lock().unwrap().push(fallback);
+        }
+    }
+
+    /// Plays two moves with a delegate failing in the given way,
+    /// returning the sent moves and whether the delegate saw them as fallbacks.
+    fn play_flaky(failure: Failure) -> (Vec<String>, Vec<bool>) {
+        let state: State = "10000000/00000000/00000000/00000000/00000000/00000000/00000000/00000001 R 0 0:0 -".parse().unwrap();
+        let event = |event: Event| Element::from(event).to_string();
+        let room = |payload| event(Event::Room { room_id: "a".to_owned(), payload });
+        let request = format!("{}{}", room(EventPayload::Memento(state)), room(EventPayload::MoveRequest));
+        let input = format!(
+            "<protocol>{}{}{}{}{}</protocol>",
+            event(Event::Joined { room_id: "a".to_owned() }),
+            request,
+            request,
+            room(EventPayload::GameResult(GameResult::new(ScoreDefinition::new([]), HashMap::new(), None))),
+            event(Event::Left { room_id: "a".to_owned() }),
+        );
+
+        let mut output = Vec::new();
+        let debug_mode = DebugMode { debug_reader: false, debug_writer: false };
+        let fallbacks = Arc::new(Mutex::new(Vec::new()));
+        let client = GameClient::new(FlakyDelegate { failure, calls: 0, fallbacks: fallbacks.clone() }, debug_mode, None)
+            .with_move_timeout(Duration::from_millis(50));
+        assert_eq!(client.run(Cursor::new(input), &mut output).unwrap().len(), 1);
+        let moves = String::from_utf8(output).unwrap()
+            .split(r#"<data class="move">"#)
+            .skip(1)
+            .map(|m| m.to_owned())
+            .collect();
+        let fallbacks = fallbacks.lock().unwrap().clone();
+        (moves, fallbacks)
+    }
+
+    #[test]
+    fn test_delegate_failures() {
+        // The failed move is replaced by the fallback, which picks the last
+        // of the equally good floes, then the same delegate answers again
+        let (moves, fallbacks) = play_flaky(Failure::Panic);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(fallbacks, [true, false]);
+        assert!(moves[0].contains(r#"x="15""#) && moves[0].contains(r#"y="7""#));
+        assert!(moves[1].contains(r#"x="0""#) && moves[1].contains(r#"y="0""#));
+
+        // A delegate that never returns gets no further requests,
+        // the client keeps answering with fallbacks instead
+        let (moves, fallbacks) = play_flaky(Failure::Hang);
+        assert_eq!(moves.len(), 2);
+        assert!(fallbacks.is_empty());
+        assert!(moves.iter().all(|m| m.contains(r#"x="15""#) && m.contains(r#"y="7""#)));
+    }
+
+    /// Overruns on its first move and picks the first legal move afterwards.
+    struct SlowDelegate {
+        finished: Arc<AtomicBool>,
+        fallbacks: Arc<Mutex<Vec<bool>>>,
+    }
+
+    impl GameClientDelegate for SlowDelegate {
+        fn request_move(&mut self, state: &State, _my_team: Team) -> Move {
+            if !self.finished.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(500));
+                self.finished.store(true, Ordering::SeqCst);
+            }
+            state.possible_moves()[0]
+        }
+
+        fn on_move_sent(&mut self, _state: &State, _m: Move, _duration: Duration, fallback: bool) {
+            self.fallbacks.lock().unwrap().push(fallback);
+        }
+    }
+
+    #[test]
+    fn test_move_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let finished = Arc::new(AtomicBool::new(false));
+        let fallbacks = Arc::new(Mutex::new(Vec::new()));
+        let delegate = SlowDelegate { finished: finished.clone(), fallbacks: fallbacks.clone() };
+        let client = thread::spawn(move || {
+            let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let debug_mode = DebugMode { debug_reader: false, debug_writer: false };
+            GameClient::new(delegate, debug_mode, None)
+                .with_move_timeout(Duration::from_millis(50))
+                .run(stream.try_clone().unwrap(), stream)
+                .unwrap()
+        });
+
+        let state: State = "10000000/00000000/00000000/00000000/00000000/00000000/00000000/00000001 R 0 0:0 -".parse().unwrap();
+        let event = |event: Event| Element::from(event).to_string();
+        let room = |payload| event(Event::Room { room_id: "a".to_owned(), payload });
+        let (mut stream, _) = listener.accept().unwrap();
+        write!(stream, "<protocol>{}{}{}", event(Event::Joined { room_id: "a".to_owned() }), room(EventPayload::Memento(state)), room(EventPayload::MoveRequest)).unwrap();
+
+        // The fallback, which picks the last of the equally good floes,
+        // arrives while the slow delegate is still computing
+        let received = read_until(&mut stream, "</room>");
+        assert!(!finished.load(Ordering::SeqCst), "The move was sent only after the delegate returned");
+        assert!(received.contains(r#"x="15""#) && received.contains(r#"y="7""#));
+
+        // Once the delegate is done, it answers the next request
+        // and learns about the fallback sent in its place
+        while !finished.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        thread::sleep(Duration::from_millis(50));
+        write!(stream, "{}{}", room(EventPayload::Memento(state)), room(EventPayload::MoveRequest)).unwrap();
+        let received = read_until(&mut stream, "</room>");
+        assert!(received.contains(r#"x="0""#) && received.contains(r#"y="0""#));
+
+        write!(stream, "{}{}</protocol>", room(EventPayload::GameResult(GameResult::new(ScoreDefinition::new([]), HashMap::new(), None))), event(Event::Left { room_id: "a".to_owned() })).unwrap();
+        assert_eq!(client.join().unwrap().len(), 1);
+        assert_eq!(*fallbacks.lock().unwrap(), [true, false]);
     }
 }
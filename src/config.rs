@@ -0,0 +1,288 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use log::LevelFilter;
+use socha_client_2023::client::DEFAULT_MOVE_TIMEOUT;
+use socha_client_2023::minimax::DEFAULT_BUDGET;
+use socha_client_2023::strategy::StrategySpec;
+
+/// An invalid config file or setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// The line of the config file, if the error stems from one.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self { line, message: message.into() }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The settings of the client. They are read from a config file of
+/// `[section]` headers and `key = value` lines, where values may be
+/// quoted and `#` starts a comment, and can be overridden from the
+/// command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    // [connection]
+    pub host: String,
+    pub port: u16,
+    pub reservation: Option<String>,
+    pub room: Option<String>,
+    /// The number of games to play, 0 to play until the server disconnects.
+    pub games: usize,
+    /// The number of attempts to reconnect, 0 to give up immediately.
+    pub reconnect: u32,
+    pub move_timeout: Duration,
+    // [log]
+    pub level: LevelFilter,
+    // [engine]
+    /// The strategy that picks the moves, with its options.
+    pub strategy: StrategySpec,
+    /// The time a time-bound strategy may spend on a move,
+    /// unless its `millis` option says otherwise.
+    pub time_budget: Duration,
+    /// A learned pattern table to evaluate penguin neighborhoods with.
+    pub patterns: Option<PathBuf>,
+    // [recording]
+    pub replay_dir: Option<PathBuf>,
+    /// A CSV file to append evaluation features of each game to.
+    pub game_data: Option<PathBuf>,
+    /// A file to append pattern training samples of each game to, none by default.
+    pub pattern_samples: Option<PathBuf>,
+    /// A JSON Lines file to append statistics of each move and game to.
+    pub telemetry: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_owned(),
+            port: 13050,
+            reservation: None,
+            room: None,
+            games: 1,
+            reconnect: 5,
+            move_timeout: DEFAULT_MOVE_TIMEOUT,
+            level: LevelFilter::Info,
+            strategy: StrategySpec::default(),
+            time_budget: DEFAULT_BUDGET,
+            patterns: None,
+            replay_dir: None,
+            game_data: Some(PathBuf::from("gamedata_new_new_2.csv")),
+            pattern_samples: None,
+            telemetry: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file at the given path on top of the defaults.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| ConfigError::new(None, format!("Could not read {}: {}", path.display(), e)))?;
+        text.parse()
+    }
+
+    /// Checks that the settings fit together.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.reservation.is_some() && self.room.is_some() {
+            return Err(ConfigError::new(None, "Only one of reservation and room can be given"));
+        }
+        let budget = match self.strategy.options.get::<u64>("millis") {
+            Ok(millis) => millis.map(Duration::from_millis).unwrap_or(self.time_budget),
+            Err(e) => return Err(ConfigError::new(None, format!("Invalid strategy {}: {}", self.strategy, e))),
+        };
+        if self.strategy.name == "patterns" && self.strategy.options.value("table").is_none() && self.patterns.is_none() {
+            return Err(ConfigError::new(None, "The patterns strategy needs a pattern table, given by its table option or by patterns in [engine]"));
+        }
+        if budget >= self.move_timeout {
+            return Err(ConfigError::new(None, format!(
+                "The time budget ({} ms) must be below move_timeout_ms ({}), or fallback moves are sent",
+                budget.as_millis(),
+                self.move_timeout.as_millis(),
+            )));
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, section: &str, key: &str, value: &str, line: usize) -> Result<(), ConfigError> {
+        let path = |value: &str| Some(value).filter(|v| !v.is_empty()).map(PathBuf::from);
+        match (section, key) {
+            ("connection", "host") => self.host = value.to_owned(),
+            ("connection", "port") => self.port = parse_value(key, value, line)?,
+            ("connection", "reservation") => self.reservation = Some(value.to_owned()),
+            ("connection", "room") => self.room = Some(value.to_owned()),
+            ("connection", "games") => self.games = parse_value(key, value, line)?,
+            ("connection", "reconnect") => self.reconnect = parse_value(key, value, line)?,
+            ("connection", "move_timeout_ms") => self.move_timeout = Duration::from_millis(parse_value(key, value, line)?),
+            ("log", "level") => self.level = parse_value(key, value, line)?,
+            ("engine", "strategy") => self.strategy = parse_value(key, value, line)?,
+            ("engine", "time_budget_ms") => self.time_budget = Duration::from_millis(parse_value(key, value, line)?),
+            ("engine", "patterns") => self.patterns = path(value),
+            ("recording", "replay_dir") => self.replay_dir = path(value),
+            ("recording", "game_data") => self.game_data = path(value),
+            ("recording", "pattern_samples") => self.pattern_samples = path(value),
+            ("recording", "telemetry") => self.telemetry = path(value),
+            ("", _) => return Err(ConfigError::new(Some(line), format!("Setting {} is outside of a section", key))),
+            _ => return Err(ConfigError::new(Some(line), format!("Unknown setting {} in [{}]", key, section))),
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    /// Parses a config file, starting from the defaults.
+    fn from_str(s: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        let mut section = String::new();
+        for (i, raw) in s.lines().enumerate() {
+            let line = i + 1;
+            let content = strip_comment(raw).trim();
+            if content.is_empty() {
+                continue;
+            }
+            if let Some(name) = content.strip_prefix('[') {
+                let name = name.strip_suffix(']')
+                    .ok_or_else(|| ConfigError::new(Some(line), format!("Unclosed section header '{}'", content)))?;
+                if !["connection", "log", "engine", "recording"].contains(&name.trim()) {
+                    return Err(ConfigError::new(Some(line), format!("Unknown section [{}]", name.trim())));
+                }
+                section = name.trim().to_owned();
+                continue;
+            }
+            let (key, value) = content.split_once('=')
+                .ok_or_else(|| ConfigError::new(Some(line), format!("Expected key = value, found '{}'", content)))?;
+            config.set(&section, key.trim(), &unquote(value.trim(), line)?, line)?;
+        }
+        Ok(config)
+    }
+}
+
+/// Parses a value with the type of the given setting.
+fn parse_value<T>(key: &str, value: &str, line: usize) -> Result<T, ConfigError> where T: FromStr, T::Err: fmt::Display {
+    value.parse().map_err(|e| ConfigError::new(Some(line), format!("Invalid value '{}' for {}: {}", value, key, e)))
+}
+
+/// Cuts off a `#` comment outside of quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+/// Removes the quotes around a value and resolves
+/// the escapes `\"` and `\\` inside of them.
+fn unquote(value: &str, line: usize) -> Result<String, ConfigError> {
+    let Some(inner) = value.strip_prefix('"') else {
+        return Ok(value.to_owned());
+    };
+    let mut result = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if chars.as_str().is_empty() => return Ok(result),
+            '"' => break,
+            '\\' => match chars.next() {
+                Some(escaped @ ('"' | '\\')) => result.push(escaped),
+                _ => return Err(ConfigError::new(Some(line), format!("Invalid escape in {}", value))),
+            },
+            c => result.push(c),
+        }
+    }
+    Err(ConfigError::new(Some(line), format!("Invalid quoted value {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use indoc::indoc;
+    use log::LevelFilter;
+
+    use super::{Config, ConfigError};
+
+    #[test]
+    fn test_parse() {
+        let config: Config = indoc! {r#"
+            # Tournament settings
+            [connection]
+            host = "server.example" # the official server
+            port = 13051
+            reservation = "a#b"
+
+            [log]
+            level = debug
+
+            [engine]
+            strategy = "mcts:seed=3, millis=1000"
+            time_budget_ms = 1500
+
+            [recording]
+            replay_dir = "replays/\"quoted\""
+            game_data = ""
+            telemetry = telemetry.jsonl
+        "#}.parse().unwrap();
+        assert_eq!(config, Config {
+            host: "server.example".to_owned(),
+            port: 13051,
+            reservation: Some("a#b".to_owned()),
+            level: LevelFilter::Debug,
+            strategy: "mcts:seed=3,millis=1000".parse().unwrap(),
+            time_budget: Duration::from_millis(1500),
+            replay_dir: Some(PathBuf::from(r#"replays/"quoted""#)),
+            game_data: None,
+            telemetry: Some(PathBuf::from("telemetry.jsonl")),
+            ..Config::default()
+        });
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |s: &str| s.parse::<Config>().unwrap_err();
+        assert_eq!(error("[connection]\nport = 70000").line, Some(2));
+        assert_eq!(error("port = 1").message, "Setting port is outside of a section");
+        assert_eq!(error("[engine]\n\nstrategy = deep_blue").line, Some(3));
+        assert!(error("[engine]\nstrategy = minimax:width=2").message.contains("Unknown strategy option 'width'"));
+        assert_eq!(error("[engine]\ndepth = 3").message, "Unknown setting depth in [engine]");
+        assert_eq!(error("[network]"), ConfigError { line: Some(1), message: "Unknown section [network]".to_owned() });
+        assert_eq!(error("[log\nlevel = info").line, Some(1));
+        assert_eq!(error("[log]\nlevel").line, Some(2));
+        assert_eq!(error("[log]\nlevel = \"info").line, Some(2));
+
+        let invalid = |s: &str| s.parse::<Config>().unwrap().validate().unwrap_err();
+        assert!(invalid("[engine]\ntime_budget_ms = 2000").message.contains("must be below move_timeout_ms"));
+        assert!(invalid("[engine]\nstrategy = mcts:millis=2000").message.contains("must be below move_timeout_ms"));
+        assert!(invalid("[engine]\nstrategy = dynmax:millis=soon").message.contains("Invalid strategy"));
+        assert!(invalid("[connection]\nreservation = a\nroom = b").message.contains("Only one"));
+        assert!(invalid("[engine]\nstrategy = patterns").message.contains("needs a pattern table"));
+        assert_eq!("[engine]\nstrategy = patterns\npatterns = patterns.txt".parse::<Config>().unwrap().validate(), Ok(()));
+    }
+}
@@ -1,10 +1,11 @@
 use std::{ops::{Index, IndexMut}, fmt, str::FromStr};
 
 use arrayvec::ArrayVec;
+use rand::Rng;
 
 use crate::util::{Element, Error, Result};
 
-use super::{Field, BOARD_FIELDS, Vec2, Direct, BOARD_SIZE, Move, Doubled, Team, field};
+use super::{Field, BOARD_FIELDS, TEAMS, PENGUINS_PER_TEAM, Vec2, Direct, BOARD_SIZE, Move, Doubled, Team, field};
 
 // Ported from https://github.com/software-challenge/backend/blob/a3145a91749abb73ca5ffd426fd2a77d9a90967a/plugin/src/main/kotlin/sc/plugin2023/Board.kt
 
@@ -29,6 +30,30 @@ impl Board {
         Self { fields }
     }
 
+    /// Generates a random board, point-symmetric like the boards of the
+    /// official server, with enough one-fish floes to place all penguins.
+    pub fn random(rng: &mut impl Rng) -> Self {
+        /// The relative frequencies of 0 to 4 fish on a floe.
+        const WEIGHTS: [u32; 5] = [1, 4, 3, 2, 1];
+        loop {
+            let mut fields = [Field::EMPTY; BOARD_FIELDS];
+            for i in 0..BOARD_FIELDS / 2 {
+                let mut roll = rng.gen_range(0..WEIGHTS.iter().sum::<u32>());
+                let mut fish = 0;
+                while roll >= WEIGHTS[fish] {
+                    roll -= WEIGHTS[fish];
+                    fish += 1;
+                }
+                fields[i] = Field::with_fish(fish);
+                fields[BOARD_FIELDS - 1 - i] = Field::with_fish(fish);
+            }
+            let board = Self::new(fields);
+            if board.fields().filter(|(_, f)| f.fish() == 1).count() >= TEAMS * PENGUINS_PER_TEAM {
+                return board;
+            }
+        }
+    }
+
     /// Checks whether the given coordinates are in bounds.
     pub fn in_bounds(coords: impl Into<Vec2<Doubled>>) -> bool {
         let doubled: Vec2<Doubled> = coords.into();
@@ -44,13 +69,13 @@ impl Board {
     }
 
     /// Converts coordinates to an index.
-    fn index_for(coords: impl Into<Vec2<Doubled>>) -> usize {
+    pub fn index_for(coords: impl Into<Vec2<Doubled>>) -> usize {
         let direct: Vec2<Direct> = coords.into().into();
         direct.y as usize * BOARD_SIZE + direct.x as usize
     }
 
     /// Converts an index to coordinates.
-    fn coords_for(index: usize) -> Vec2<Direct> {
+    pub fn coords_for(index: usize) -> Vec2<Direct> {
         Vec2::new((index % BOARD_SIZE) as i32, (index / BOARD_SIZE) as i32)
     }
 
@@ -128,11 +153,21 @@ impl FromStr for Board {
                 .flat_map(|l| l.chars().map(|c| c.try_into()))
                 .collect::<Result<ArrayVec<Field, BOARD_FIELDS>>>()?
                 .into_inner()
-                .map_err(|e| Error::from(format!("Board has wrong number of fields: {:?}", e)))?
+                .map_err(|fields| Error::WrongCount { what: "fields", expected: BOARD_FIELDS, found: fields.len() })?
         })
     }
 }
 
+impl From<Board> for Element {
+    fn from(board: Board) -> Self {
+        Element::new("board")
+            .childs(board.fields.chunks(BOARD_SIZE).map(|row| Element::new("list")
+                .childs(row.iter().map(|&f| f.into()))
+                .build()))
+            .build()
+    }
+}
+
 impl TryFrom<&Element> for Board {
     type Error = Error;
 
@@ -142,7 +177,7 @@ impl TryFrom<&Element> for Board {
                 .flat_map(|c| c.childs_by_name("field").map(|c| c.try_into()))
                 .collect::<Result<ArrayVec<Field, BOARD_FIELDS>>>()?
                 .into_inner()
-                .map_err(|e| Error::from(format!("Board has wrong number of fields: {:?}", e)))?
+                .map_err(|fields| Error::WrongCount { what: "fields", expected: BOARD_FIELDS, found: fields.len() })?
         })
     }
 }
@@ -153,6 +188,8 @@ mod tests {
 
     use indoc::indoc;
 
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
     use crate::{util::Element, game::{Board, Team, Vec2, Field, Direct}};
 
     #[test]
@@ -272,4 +309,33 @@ mod tests {
 
         assert_eq!(board.to_string().parse::<Board>().unwrap(), board);
     }
+
+    #[test]
+    fn test_random() {
+        let mut rng = StdRng::seed_from_u64(2023);
+        for _ in 0..20 {
+            let board = Board::random(&mut rng);
+            for (c, f) in board.fields() {
+                let direct = c.to_direct();
+                assert_eq!(board[Vec2::<Direct>::new(7 - direct.x, 7 - direct.y)], f);
+                assert!(f.fish() <= 4 && f.penguin().is_none());
+            }
+            assert!(board.fields().filter(|(_, f)| f.fish() == 1).count() >= 8);
+        }
+    }
+
+    #[test]
+    fn test_xml_roundtrip() {
+        let mut rng = StdRng::seed_from_u64(43);
+        for _ in 0..50 {
+            let mut board = Board::random(&mut rng);
+            for _ in 0..rng.gen_range(0..=8) {
+                let team = if rng.gen() { Team::One } else { Team::Two };
+                board[Vec2::<Direct>::new(rng.gen_range(0..8), rng.gen_range(0..8))] = Field::with_penguin(team);
+            }
+            let xml = Element::from(board);
+            assert_eq!(Board::try_from(&xml).unwrap(), board);
+            assert_eq!(Board::try_from(&xml.to_string().parse::<Element>().unwrap()).unwrap(), board);
+        }
+    }
 }
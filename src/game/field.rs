@@ -96,12 +96,12 @@ impl TryFrom<char> for Field {
 
     fn try_from(c: char) -> Result<Self> {
         if c.is_alphabetic() {
-            let team = Team::with_letter(c).ok_or_else(|| Error::Custom(format!("Not a team: {}", c)))?;
+            let team = Team::with_letter(c).ok_or_else(|| Error::UnknownVariant { kind: "team", found: c.to_string() })?;
             Ok(Field::with_penguin(team))
         } else if let Some(fish) = c.to_digit(10) {
             Ok(Field::with_fish(fish as usize))
         } else {
-            Err(Error::Custom(format!("Invalid field: {}", c)))
+            Err(Error::InvalidNotation { expected: "a fish count or a team letter", found: c.to_string() })
         }
     }
 }
@@ -116,3 +116,13 @@ impl TryFrom<&Element> for Field {
         })
     }
 }
+
+impl From<Field> for Element {
+    fn from(field: Field) -> Self {
+        let content = match field.penguin {
+            Some(team) => team.to_string(),
+            None => field.fish.to_string(),
+        };
+        Element::new("field").content(&content).build()
+    }
+}
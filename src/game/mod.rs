@@ -2,6 +2,8 @@ mod board;
 mod constants;
 mod field;
 mod r#move;
+mod move_error;
+mod notation;
 mod state;
 mod team;
 mod vec2;
@@ -10,6 +12,8 @@ pub use board::*;
 pub use constants::*;
 pub use field::*;
 pub use r#move::*;
+pub use move_error::*;
+pub use notation::*;
 pub use state::*;
 pub use team::*;
 pub use vec2::*;
@@ -46,11 +46,17 @@ impl Move {
 }
 
 impl fmt::Display for Move {
+    /// Formats the move in the named-square notation, falling back to
+    /// doubled coordinates for fields outside of the board (`(3,5)->(7,5)`
+    /// or `->(7,5)`). Neither contains whitespace, so moves can be
+    /// embedded in the state notation.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(from) = self.from {
-            write!(f, "{} -> {}", from, self.to)
+        if let Some(notation) = self.notation() {
+            write!(f, "{}", notation)
+        } else if let Some(from) = self.from {
+            write!(f, "({},{})->({},{})", from.x, from.y, self.to.x, self.to.y)
         } else {
-            write!(f, "-> {}", self.to)
+            write!(f, "->({},{})", self.to.x, self.to.y)
         }
     }
 }
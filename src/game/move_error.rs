@@ -0,0 +1,39 @@
+use std::fmt;
+
+use super::{Team, Vec2, Doubled};
+
+/// The reason why a move is not legal in a given state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    /// The target (or source) is not on the board.
+    OutOfBounds(Vec2<Doubled>),
+    /// The moved penguin does not belong to the current team.
+    WrongTeam { expected: Team, found: Option<Team> },
+    /// A penguin was moved before all penguins were placed.
+    NotAllPlaced,
+    /// A penguin was placed after all penguins were placed.
+    AllPlaced,
+    /// A penguin was placed on a field without exactly one fish.
+    InvalidPlacement { fish: usize },
+    /// The move does not follow one of the six hex directions.
+    NotStraight,
+    /// The path crosses water or another penguin.
+    PathBlocked(Vec2<Doubled>),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds(c) => write!(f, "{} is out of bounds", c),
+            Self::WrongTeam { expected, found: Some(found) } => write!(f, "Expected a penguin of team {}, found one of {}", expected, found),
+            Self::WrongTeam { expected, found: None } => write!(f, "Expected a penguin of team {}, found none", expected),
+            Self::NotAllPlaced => write!(f, "Cannot move until all penguins have been placed"),
+            Self::AllPlaced => write!(f, "Cannot place after all penguins have been placed"),
+            Self::InvalidPlacement { fish } => write!(f, "Can only place on a field with one fish, not {}", fish),
+            Self::NotStraight => write!(f, "Can only move in straight lines"),
+            Self::PathBlocked(c) => write!(f, "Path is blocked at {}", c),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use crate::util::{Error, Result};
+
+use super::{Board, Move, Vec2, Direct, Doubled, BOARD_SIZE};
+
+// Fields are named by a column letter and a row number in direct
+// coordinates, i.e. `a1` is the top left and `h8` the bottom right field.
+// Moves are written as `d4-f4` (slides) and `d4` (placements), or with
+// a direction and distance as `d4:E2`, where the direction is one of
+// E, W, NE, NW, SE and SW as seen on the rendered board. Moves involving
+// fields without a name are written in doubled coordinates as
+// `(3,5)->(7,5)` and `->(7,5)`.
+
+/// The names of the hex directions, as seen on the rendered board.
+pub const DIRECTION_NAMES: [(&str, Vec2<Doubled>); 6] = [
+    ("E", Vec2::<Doubled>::LEFT),
+    ("W", Vec2::<Doubled>::RIGHT),
+    ("NE", Vec2::<Doubled>::UP_RIGHT),
+    ("NW", Vec2::<Doubled>::UP_LEFT),
+    ("SE", Vec2::<Doubled>::DOWN_RIGHT),
+    ("SW", Vec2::<Doubled>::DOWN_LEFT),
+];
+
+/// The name of the given field, if it is on the board and
+/// has valid doubled coordinates.
+pub fn square_name(coords: Vec2<Doubled>) -> Option<String> {
+    if !Board::in_bounds(coords) || coords.x % 2 != coords.y % 2 {
+        return None;
+    }
+    let direct = coords.to_direct();
+    Some(format!("{}{}", (b'a' + direct.x as u8) as char, direct.y + 1))
+}
+
+/// Parses a field name like `d4`.
+pub fn parse_square(s: &str) -> Result<Vec2<Doubled>> {
+    let invalid = || Error::InvalidNotation { expected: "a square from a1 to h8", found: s.to_owned() };
+    let mut chars = s.chars();
+    let column = chars.next()
+        .filter(|c| ('a'..='h').contains(c))
+        .ok_or_else(invalid)?;
+    let row: usize = chars.as_str().parse().map_err(|_| invalid())?;
+    if !(1..=BOARD_SIZE).contains(&row) {
+        return Err(invalid());
+    }
+    Ok(Vec2::<Direct>::new(column as i32 - 'a' as i32, row as i32 - 1).into())
+}
+
+/// Parses doubled coordinates like `(7,5)`.
+pub fn parse_coordinates(s: &str) -> Result<Vec2<Doubled>> {
+    let invalid = || Error::InvalidNotation { expected: "doubled coordinates like (7,5)", found: s.to_owned() };
+    let (x, y) = s.strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .and_then(|s| s.split_once(','))
+        .ok_or_else(invalid)?;
+    Ok(Vec2::new(x.parse()?, y.parse()?))
+}
+
+/// The name of the given direction.
+pub fn direction_name(direction: Vec2<Doubled>) -> Option<&'static str> {
+    DIRECTION_NAMES.iter().find(|(_, d)| *d == direction).map(|(n, _)| *n)
+}
+
+/// Parses a direction name like `NE`, ignoring case.
+pub fn parse_direction(s: &str) -> Result<Vec2<Doubled>> {
+    DIRECTION_NAMES.iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(s))
+        .map(|(_, d)| *d)
+        .ok_or_else(|| Error::UnknownVariant { kind: "direction", found: s.to_owned() })
+}
+
+impl Move {
+    /// Formats the move in the named-square notation (`d4-f4` or `d4`),
+    /// if both fields are on the board.
+    pub fn notation(self) -> Option<String> {
+        let to = square_name(self.to())?;
+        match self.from() {
+            Some(from) => Some(format!("{}-{}", square_name(from)?, to)),
+            None => Some(to),
+        }
+    }
+
+    /// Formats a slide in the direction notation (`d4:E2`),
+    /// if the move is a straight slide from a field on the board.
+    pub fn direction_notation(self) -> Option<String> {
+        let from = self.from()?;
+        let delta = self.to() - from;
+        if delta == Vec2::ZERO || !delta.straight() {
+            return None;
+        }
+        let distance = if delta.y == 0 { delta.x.abs() / 2 } else { delta.y.abs() };
+        Some(format!("{}:{}{}", square_name(from)?, direction_name(delta / distance)?, distance))
+    }
+}
+
+impl FromStr for Move {
+    type Err = Error;
+
+    /// Parses a move in the named-square, the direction
+    /// or the doubled coordinate notation.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some((from, to)) = s.split_once("->") {
+            let to = parse_coordinates(to)?;
+            if from.is_empty() {
+                Ok(Move::placing(to))
+            } else {
+                Ok(Move::between(parse_coordinates(from)?, to))
+            }
+        } else if let Some((from, by)) = s.split_once(':') {
+            let invalid = || Error::InvalidNotation { expected: "a slide with a positive distance like d4:E2", found: s.to_owned() };
+            let split = by.find(|c: char| c.is_ascii_digit()).ok_or_else(invalid)?;
+            let (direction, distance) = by.split_at(split);
+            let distance: i32 = distance.parse()?;
+            if distance < 1 {
+                return Err(invalid());
+            }
+            Ok(Move::sliding(parse_square(from)?, parse_direction(direction)? * distance))
+        } else if let Some((from, to)) = s.split_once('-') {
+            Ok(Move::between(parse_square(from)?, parse_square(to)?))
+        } else {
+            Ok(Move::placing(parse_square(s)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Move, Vec2, Direct, Doubled};
+
+    use super::{square_name, parse_square};
+
+    #[test]
+    fn test_squares() {
+        assert_eq!(square_name(Vec2::<Direct>::new(0, 0).into()), Some("a1".to_owned()));
+        assert_eq!(square_name(Vec2::<Direct>::new(7, 7).into()), Some("h8".to_owned()));
+        assert_eq!(square_name(Vec2::<Doubled>::new(7, 5)), Some("d6".to_owned()));
+        assert_eq!(square_name(Vec2::<Doubled>::new(6, 5)), None);
+        assert_eq!(square_name(Vec2::<Doubled>::new(16, 0)), None);
+        for y in 0..8 {
+            for x in 0..8 {
+                let coords: Vec2<Doubled> = Vec2::<Direct>::new(x, y).into();
+                assert_eq!(parse_square(&square_name(coords).unwrap()).unwrap(), coords);
+            }
+        }
+        assert!(parse_square("i1").is_err());
+        assert!(parse_square("a0").is_err());
+        assert!(parse_square("a9").is_err());
+        assert!(parse_square("A1").is_err());
+    }
+
+    #[test]
+    fn test_move_notation() {
+        let slide = Move::between(Vec2::<Doubled>::new(3, 5), Vec2::<Doubled>::new(7, 5));
+        assert_eq!(slide.notation().as_deref(), Some("b6-d6"));
+        assert_eq!(slide.direction_notation().as_deref(), Some("b6:E2"));
+        assert_eq!("b6-d6".parse::<Move>().unwrap(), slide);
+        assert_eq!("b6:E2".parse::<Move>().unwrap(), slide);
+        assert_eq!(" b6:e2 ".parse::<Move>().unwrap(), slide);
+
+        let diagonal = Move::between(Vec2::<Doubled>::new(8, 4), Vec2::<Doubled>::new(5, 1));
+        assert_eq!(diagonal.direction_notation().as_deref(), Some("e5:NW3"));
+        assert_eq!("e5:NW3".parse::<Move>().unwrap(), diagonal);
+
+        let placement = Move::placing(Vec2::<Doubled>::new(13, 5));
+        assert_eq!(placement.notation().as_deref(), Some("g6"));
+        assert_eq!(placement.direction_notation(), None);
+        assert_eq!("g6".parse::<Move>().unwrap(), placement);
+
+        let off_board = Move::between(Vec2::<Doubled>::new(-1, 5), Vec2::<Doubled>::new(16, 5));
+        assert_eq!(off_board.to_string(), "(-1,5)->(16,5)");
+        assert_eq!("(-1,5)->(16,5)".parse::<Move>().unwrap(), off_board);
+        let odd = Move::placing(Vec2::<Doubled>::new(6, 5));
+        assert_eq!(odd.to_string(), "->(6,5)");
+        assert_eq!("->(6,5)".parse::<Move>().unwrap(), odd);
+
+        for invalid in ["", "b6-", "b6:E", "b6:E0", "b6:X2", "z6-d6", "->", "(1,2)->", "->(1 2)", "->(1,x)"] {
+            assert!(invalid.parse::<Move>().is_err(), "{} should not parse", invalid);
+        }
+    }
+}
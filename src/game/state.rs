@@ -1,10 +1,12 @@
 use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
 
 use arrayvec::ArrayVec;
 
 use crate::util::{Element, Error, Result};
 
-use super::{Board, Move, Team, PENGUINS_PER_TEAM, TEAMS, Vec2, Field, Doubled, BOARD_FIELDS, board};
+use super::{Board, Move, MoveError, Team, PENGUINS_PER_TEAM, TEAMS, Vec2, Field, Doubled, BOARD_FIELDS, board};
 
 // Ported from https://github.com/software-challenge/backend/blob/a3145a91749abb73ca5ffd426fd2a77d9a90967a/plugin/src/main/kotlin/sc/plugin2023/GameState.kt
 
@@ -26,6 +28,12 @@ pub struct State {
 }
 
 impl State {
+    /// Creates a new state, counting the fish left on the board.
+    pub fn new(board: Board, turn: usize, fish: [usize; TEAMS], last_move: Option<Move>, start_team: Team) -> Self {
+        let fish_left = board.fields().map(|(_, f)| f.fish()).sum();
+        Self { board, turn, fish, last_move, start_team, fish_left }
+    }
+
     /// Fetches the board.
     pub fn board(&self) -> &Board { &self.board }
 
@@ -138,6 +146,53 @@ impl State {
         }
     }
 
+    /// Checks whether the given move is legal for the current team.
+    pub fn check_move(&self, m: Move) -> std::result::Result<(), MoveError> {
+        let team = self.current_team();
+        let to = m.to();
+        if !Board::in_bounds(to) {
+            return Err(MoveError::OutOfBounds(to));
+        }
+        if let Some(from) = m.from() {
+            if !Board::in_bounds(from) {
+                return Err(MoveError::OutOfBounds(from));
+            }
+            let found = self.board[from].penguin();
+            if found != Some(team) {
+                return Err(MoveError::WrongTeam { expected: team, found });
+            }
+            if !self.penguins_placed() {
+                return Err(MoveError::NotAllPlaced);
+            }
+            let delta = to - from;
+            if delta == Vec2::ZERO || !delta.straight() {
+                return Err(MoveError::NotStraight);
+            }
+            let steps = if delta.y == 0 { delta.x.abs() / 2 } else { delta.y.abs() };
+            let direction = delta / steps;
+            for i in 1..=steps {
+                let field = from + i * direction;
+                if self.board[field].fish() == 0 {
+                    return Err(MoveError::PathBlocked(field));
+                }
+            }
+        } else {
+            if self.penguins_placed() {
+                return Err(MoveError::AllPlaced);
+            }
+            let fish = self.board[to].fish();
+            if fish != 1 {
+                return Err(MoveError::InvalidPlacement { fish });
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the given move is legal for the current team.
+    pub fn is_legal(&self, m: Move) -> bool {
+        self.check_move(m).is_ok()
+    }
+
     /// Performs the given move.
     pub fn perform(&mut self, m: Move) -> usize {
         let to = m.to();
@@ -193,12 +248,12 @@ impl TryFrom<&Element> for State {
 
         Ok(State {
             board: elem.child_by_name("board")?.try_into()?,
-            turn: elem.attribute("turn")?.parse()?,
+            turn: elem.parse_attribute("turn")?,
             fish: elem.child_by_name("fishes")?
                 .childs_by_name("int").map(|c| Ok(c.content().parse()?))
                 .collect::<Result<ArrayVec<usize, TEAMS>>>()?
                 .into_inner()
-                .map_err(|e| Error::from(format!("State has wrong number of fish teams: {:?}", e)))?,
+                .map_err(|fish| Error::WrongCount { what: "fish counts", expected: TEAMS, found: fish.len() })?,
             last_move: elem.child_by_name("lastMove").ok().and_then(|m| m.try_into().ok()),
             start_team: elem.child_by_name("startTeam")?.content().parse()?,
             fish_left: out,
@@ -206,13 +261,80 @@ impl TryFrom<&Element> for State {
     }
 }
 
+impl From<State> for Element {
+    fn from(state: State) -> Self {
+        let start_team = state.start_team.to_string();
+        let fish = state.fish.map(|f| f.to_string());
+        Element::new("state")
+            .attribute("class", "state")
+            .attribute("turn", state.turn)
+            .child(Element::new("startTeam").content(&start_team))
+            .child(state.board)
+            .option_child(state.last_move.map(|m| Element::new("lastMove")
+                .option_child(m.from().map(|v| Element::new("from").attribute("x", v.x).attribute("y", v.y)))
+                .child(Element::new("to").attribute("x", m.to().x).attribute("y", m.to().y))))
+            .child(Element::new("fishes")
+                .childs(fish.iter().map(|f| Element::new("int").content(f).build())))
+            .build()
+    }
+}
+
+/// Formats the state in a single line notation, consisting of the board
+/// rows separated by `/`, the letter of the starting team, the turn, the
+/// fish of both teams and the last move in move notation (or `-`), e.g.
+/// `R1000000/02000000/00000000/00000000/00000000/00000000/00000000/00000000 R 1 1:0 a1`.
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.board.to_string().lines().enumerate() {
+            if i > 0 {
+                write!(f, "/")?;
+            }
+            write!(f, "{}", row)?;
+        }
+        write!(f, " {} {} {}:{} ", self.start_team.letter(), self.turn, self.fish[0], self.fish[1])?;
+        match self.last_move {
+            Some(m) => write!(f, "{}", m),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+impl FromStr for State {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |part: &str, e: Error| e.context(format!("Invalid {} in state notation '{}'", part, s));
+        let parts: Vec<_> = s.split_whitespace().collect();
+        let [board, start_team, turn, fish, last_move] = parts[..] else {
+            return Err(Error::InvalidNotation { expected: "a state notation with 5 parts", found: s.to_owned() });
+        };
+        let board: Board = board.replace('/', "\n").parse().map_err(|e| invalid("board", e))?;
+        let start_team = start_team.chars().next()
+            .filter(|_| start_team.len() == 1)
+            .and_then(Team::with_letter)
+            .ok_or_else(|| invalid("starting team", Error::UnknownVariant { kind: "team", found: start_team.to_owned() }))?;
+        let turn = turn.parse().map_err(|e| invalid("turn", Error::from(e)))?;
+        let (one, two) = fish.split_once(':').ok_or_else(|| invalid("fish", Error::InvalidNotation { expected: "two fish counts like 3:5", found: fish.to_owned() }))?;
+        let fish = [
+            one.parse().map_err(|e| invalid("fish", Error::from(e)))?,
+            two.parse().map_err(|e| invalid("fish", Error::from(e)))?,
+        ];
+        let last_move = match last_move {
+            "-" => None,
+            m => Some(m.parse().map_err(|e| invalid("last move", e))?),
+        };
+        Ok(Self::new(board, turn, fish, last_move, start_team))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
     use indoc::indoc;
+    use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
 
-    use crate::{util::Element, game::{Board, Team, State, Move, Vec2, Doubled}};
+    use crate::{util::Element, game::{Board, Team, State, Move, MoveError, Vec2, Doubled}};
 
     #[test]
     fn test_from_xml() {
@@ -315,10 +437,22 @@ mod tests {
             fish: [1, 0],
             last_move: Some(Move::placing(Vec2::<Doubled>::new(13, 5))),
             start_team: Team::One,
-            fish_left: 100
+            // Counted from the board, which has no fish
+            fish_left: 0
         });
     }
 
+    #[test]
+    fn test_fish_left() {
+        let mut state: State = "10000000/00000000/00000000/00000000/00000000/00000000/00000000/00000003 R 0 0:0 -".parse().unwrap();
+        assert_eq!(state.get_fish_left(), 4);
+        let m = Move::placing(Vec2::<Doubled>::new(0, 0));
+        let fish = state.perform(m);
+        assert_eq!(state.get_fish_left(), 3);
+        state.undo_move(m, fish, Team::One);
+        assert_eq!(state.get_fish_left(), 4);
+    }
+
     #[test]
     fn test_possible_moves() {
         let board = indoc! {r#"
@@ -346,4 +480,120 @@ mod tests {
             Move::between(Vec2::<Doubled>::new(3, 7), Vec2::<Doubled>::new(1, 7)),
         ]);
     }
+
+    #[test]
+    fn test_check_move() {
+        let board = indoc! {r#"
+            00000000
+            0000000R
+            00000B00
+            0B000000
+            10R0R102
+            00010000
+            001000B0
+            1R0100B0
+        "#}.parse::<Board>().unwrap();
+        let state = State {
+            board,
+            turn: 57,
+            fish: [10, 20],
+            last_move: None,
+            start_team: Team::One,
+            fish_left: 100,
+        };
+        for m in state.possible_moves() {
+            assert_eq!(state.check_move(m), Ok(()));
+        }
+        assert_eq!(
+            state.check_move(Move::between(Vec2::<Doubled>::new(10, 2), Vec2::<Doubled>::new(11, 3))),
+            Err(MoveError::WrongTeam { expected: Team::One, found: Some(Team::Two) })
+        );
+        assert_eq!(
+            state.check_move(Move::between(Vec2::<Doubled>::new(8, 4), Vec2::<Doubled>::new(9, 6))),
+            Err(MoveError::NotStraight)
+        );
+        assert_eq!(
+            state.check_move(Move::between(Vec2::<Doubled>::new(8, 4), Vec2::<Doubled>::new(14, 4))),
+            Err(MoveError::PathBlocked(Vec2::new(12, 4)))
+        );
+        assert_eq!(
+            state.check_move(Move::between(Vec2::<Doubled>::new(8, 4), Vec2::<Doubled>::new(18, 4))),
+            Err(MoveError::OutOfBounds(Vec2::new(18, 4)))
+        );
+        assert_eq!(
+            state.check_move(Move::placing(Vec2::<Doubled>::new(0, 4))),
+            Err(MoveError::AllPlaced)
+        );
+    }
+
+    #[test]
+    fn test_check_placement() {
+        let state: State = "R1000000/02000000/00000000/00000000/00000000/00000000/00000000/00000000 R 2 0:0 -".parse().unwrap();
+        assert_eq!(state.get_fish_left(), 3);
+        assert!(state.is_legal(Move::placing(Vec2::<Doubled>::new(2, 0))));
+        assert_eq!(
+            state.check_move(Move::placing(Vec2::<Doubled>::new(3, 1))),
+            Err(MoveError::InvalidPlacement { fish: 2 })
+        );
+        assert_eq!(
+            state.check_move(Move::between(Vec2::<Doubled>::new(0, 0), Vec2::<Doubled>::new(2, 0))),
+            Err(MoveError::NotAllPlaced)
+        );
+    }
+
+    #[test]
+    fn test_notation() {
+        let board = indoc! {r#"
+            00000000
+            0000000R
+            00000B00
+            0B000000
+            10R0R102
+            00010000
+            001000B0
+            1R0100B0
+        "#}.parse::<Board>().unwrap();
+        let state = State::new(board, 57, [10, 20], Some(Move::between(Vec2::<Doubled>::new(6, 4), Vec2::<Doubled>::new(8, 4))), Team::Two);
+        let notation = "00000000/0000000R/00000B00/0B000000/10R0R102/00010000/001000B0/1R0100B0 B 57 10:20 d5-e5";
+        assert_eq!(state.to_string(), notation);
+        assert_eq!(notation.parse::<State>().unwrap(), state);
+
+        let placed = state.child(Move::between(Vec2::<Doubled>::new(8, 4), Vec2::<Doubled>::new(10, 4)));
+        assert_eq!(placed.to_string().parse::<State>().unwrap(), placed);
+        let empty = State::new(Board::EMPTY, 0, [0, 0], Some(Move::placing(Vec2::<Doubled>::new(13, 5))), Team::One);
+        assert_eq!(empty.to_string().parse::<State>().unwrap(), empty);
+        let off_board = State::new(Board::EMPTY, 0, [0, 0], Some(Move::between(Vec2::<Doubled>::new(14, 0), Vec2::<Doubled>::new(18, 0))), Team::One);
+        assert!(off_board.to_string().ends_with(" (14,0)->(18,0)"));
+        assert_eq!(off_board.to_string().parse::<State>().unwrap(), off_board);
+    }
+
+    #[test]
+    fn test_invalid_notation() {
+        for notation in [
+            "",
+            "00000000/00000000 R 1 0:0 -",
+            "00000000/00000000/00000000/00000000/00000000/00000000/00000000/00000000 X 1 0:0 -",
+            "00000000/00000000/00000000/00000000/00000000/00000000/00000000/00000000 R one 0:0 -",
+            "00000000/00000000/00000000/00000000/00000000/00000000/00000000/00000000 R 1 0 -",
+            "00000000/00000000/00000000/00000000/00000000/00000000/00000000/00000000 R 1 0:0 3,4",
+            "00000000/00000000/00000000/00000000/00000000/00000000/00000000/00000000 R 1 0:0 a1-",
+            "00000000/00000000/00000000/00000000/00000000/00000000/00000000/00000000 R 1 0:0 - extra",
+        ] {
+            assert!(notation.parse::<State>().is_err(), "{} should not parse", notation);
+        }
+    }
+
+    #[test]
+    fn test_xml_roundtrip() {
+        let mut rng = StdRng::seed_from_u64(43);
+        for _ in 0..20 {
+            let mut state = State::new(Board::random(&mut rng), 0, [0, 0], None, Team::One);
+            while let Some(&m) = state.possible_moves().choose(&mut rng) {
+                let xml = Element::from(state);
+                assert_eq!(State::try_from(&xml).unwrap(), state);
+                assert_eq!(State::try_from(&xml.to_string().parse::<Element>().unwrap()).unwrap(), state);
+                state.perform(m);
+            }
+        }
+    }
 }
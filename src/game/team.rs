@@ -78,7 +78,7 @@ impl FromStr for Team {
         match s {
             "ONE" => Ok(Self::One),
             "TWO" => Ok(Self::Two),
-            _ => Err(Error::UnknownVariant(format!("Unknown team {}", s))),
+            _ => Err(Error::UnknownVariant { kind: "team", found: s.to_owned() }),
         }
     }
 }
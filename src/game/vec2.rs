@@ -189,7 +189,7 @@ impl<C> TryFrom<&Element> for Vec2<C> where C: Copy {
     type Error = Error;
 
     fn try_from(elem: &Element) -> Result<Self> {
-        Ok(Vec2::new(elem.attribute("x")?.parse()?, elem.attribute("y")?.parse()?))
+        Ok(Vec2::new(elem.parse_attribute("x")?, elem.parse_attribute("y")?))
     }
 }
 
@@ -1,7 +1,18 @@
+pub mod admin;
 pub mod client;
+pub mod session;
+#[cfg(feature = "tokio")]
+pub mod async_client;
+pub mod replay;
 pub mod protocol;
 pub mod game;
 pub mod util;
 pub mod scoring_funcs;
 pub mod minimax;
-pub mod spots;
\ No newline at end of file
+pub mod strategy;
+pub mod telemetry;
+pub mod spots;
+pub mod patterns;
+pub mod analysis;
+pub mod render;
+pub mod svg;
\ No newline at end of file
@@ -1,14 +1,38 @@
 use array_tool::vec;
-use log::{info, debug};
-use socha_client_2023::{client::GameClientDelegate, game::{Move, Team, State}, minimax::{minimax, dyn_max, test_speed_minmax}};
-use std::{f32::INFINITY, fs::{File, OpenOptions}, path::Path, io::Write};
+use log::{info, debug, error};
+use socha_client_2023::{client::GameClientDelegate, game::{Move, Team, State}, minimax::{minimax, test_speed_minmax}, strategy::Strategy, telemetry::SearchInfo, util::Result};
+use std::{f32::INFINITY, fs::{File, OpenOptions}, path::{Path, PathBuf}, io::Write, time::Duration};
 use socha_client_2023::scoring_funcs::*;
 
-use crate::filename;
+use socha_client_2023::game::Board;
+use socha_client_2023::patterns::encode_sample;
+use socha_client_2023::render::Renderer;
+
+use crate::config::Config;
+
+/// The game logic of the client, which lets the configured strategy
+/// pick the moves and records training data of the games.
+pub struct OwnLogic {
+    strategy: Strategy,
+    /// Where to append the evaluation features of each game.
+    game_data: Option<PathBuf>,
+    /// Where to append the pattern training samples of each game.
+    pattern_samples: Option<PathBuf>,
+    /// The boards seen during the game, recorded as pattern training samples.
+    pattern_boards: Vec<Board>,
+}
 
-/// An empty game logic structure that implements the client delegate trait
-/// and thus is responsible e.g. for picking a move when requested.
-pub struct OwnLogic;
+impl OwnLogic {
+    /// Creates the logic with the strategy and recording settings of the config.
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            strategy: config.strategy.create(config.time_budget)?,
+            game_data: config.game_data.clone(),
+            pattern_samples: config.pattern_samples.clone(),
+            pattern_boards: Vec::new(),
+        })
+    }
+}
 
 
 static mut data_vec:Vec<String> = vec![];
@@ -63,29 +87,55 @@ impl GameClientDelegate for OwnLogic {
         // info!("val2: {}", get_field_levels_2(state, 1));
 
 
-        if state.turn() <= 7 {
-            //let chosen_move = find_best_start_move( *state);
-            let mut s = state.clone();
-            let chosen_move = dyn_max( s, _my_team).unwrap();
-            return chosen_move
-        }
-        else {
-            let mut s = state.clone();
-            let k = dyn_max(s, _my_team);
+        self.strategy.request_move(state, _my_team)
+    }
 
-            //info!("Chose move {}", k.unwrap());
-            return k.unwrap();
-        }
+    fn on_welcome(&mut self, team: Team) {
+        self.strategy.on_welcome(team);
+    }
 
-        
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.strategy.search_info()
+    }
+
+    fn on_move_sent(&mut self, state: &State, m: Move, duration: Duration, fallback: bool) {
+        self.strategy.on_move_sent(state, m, duration, fallback);
+    }
+
+    fn on_game_start(&mut self) {
+        self.strategy.on_game_start();
+        self.pattern_boards.clear();
     }
 
     fn on_update_state(&mut self, state: &State) {
-        debug!("Board:\n{}", state.board());
+        self.strategy.on_update_state(state);
+        debug!("Board:\n{}", Renderer::new().highlight_state(state).render(state.board()));
+        if state.turn() >= 8 && self.pattern_samples.is_some() {
+            self.pattern_boards.push(*state.board());
+        }
     }
 
 
     fn on_game_end(&mut self, _result: &socha_client_2023::protocol::GameResult) {
+        self.strategy.on_game_end(_result);
+        let result = match _result.winner() {
+            Some(winner) if winner.team() == Team::One => 1,
+            Some(_) => -1,
+            None => 0,
+        };
+        if let Some(path) = &self.pattern_samples {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(mut f) => for board in self.pattern_boards.drain(..) {
+                    if let Err(e) = writeln!(f, "{}", encode_sample(&board, result)) {
+                        error!("Could not write pattern samples to {}: {}", path.display(), e);
+                        break;
+                    }
+                },
+                Err(e) => error!("Could not open {}: {}", path.display(), e),
+            }
+        }
+        let Some(game_data) = &self.game_data else { return };
+
         unsafe {
             let mut win = 0;
             if let Some(winner) = _result.winner().clone() {
@@ -99,9 +149,9 @@ impl GameClientDelegate for OwnLogic {
                 data_vec[i] += &(";".to_owned() + &win.to_string());
             }
             let mut f = OpenOptions::new()
-                .write(true)
+                .create(true)
                 .append(true)
-                .open(filename)
+                .open(game_data)
                 .unwrap();
             for v in &data_vec {
                 if let Err(e) = writeln!(f, "{}", v) {
@@ -1,66 +1,235 @@
+mod config;
 mod logic;
-use std::path::Path;
-use std::str::FromStr;
-use clap::Parser;
-use simplelog::{SimpleLogger, Config};
-use log::LevelFilter;
-use socha_client_2023::client::{GameClient, DebugMode};
 use std::fs::OpenOptions;
-use std::io::prelude::*;
+use std::process;
+use std::time::Duration;
+use clap::Parser;
+use simplelog::{SimpleLogger, Config as LogConfig};
+use log::{info, error, LevelFilter};
+use socha_client_2023::client::{GameClient, DebugMode, Reconnect};
+use socha_client_2023::patterns::{self, PatternTable};
+use socha_client_2023::strategy::{StrategySpec, STRATEGIES};
+use socha_client_2023::telemetry::Telemetry;
 // use neuroflow::io;
 // use neuroflow::FeedForward;
 
 
+use config::Config;
 use logic::OwnLogic;
 
 /// Software Challenge 2023 client.
 #[derive(Parser, Debug)]
 struct Args {
+    /// A config file with the settings below, which override it.
+    #[clap(short, long)]
+    config: Option<String>,
     /// The game server's host address.
-    #[clap(short, long, default_value = "localhost")]
-    host: String,
+    #[clap(short, long)]
+    host: Option<String>,
     /// The game server's port.
-    #[clap(short, long, default_value_t = 13050)]
-    port: u16,
+    #[clap(short, long)]
+    port: Option<u16>,
     /// A game reservation.
     #[clap(short, long)]
     reservation: Option<String>,
+    /// The id of a room to join.
+    #[clap(long, conflicts_with = "reservation")]
+    room: Option<String>,
+    /// The number of games to play over one connection, 0 to play until the server disconnects.
+    #[clap(short, long)]
+    games: Option<usize>,
     /// The level to log at.
-    #[clap(short, long, default_value = "Info")]
-    level: String,
+    #[clap(short, long)]
+    level: Option<LevelFilter>,
     /// Reads incoming XML messages from the console for debugging.
     #[clap(short = 'd', long)]
     debug_reader: bool,
     /// Prints outgoing XML messages to the console for debugging.
     #[clap(short = 'D', long)]
     debug_writer: bool,
+    /// The strategy that picks the moves, with options like minimax:depth=4 (see --strategies).
+    #[clap(short, long)]
+    strategy: Option<StrategySpec>,
+    /// Lists the available strategies and their options.
+    #[clap(long)]
+    strategies: bool,
+    /// The time in milliseconds a time-bound strategy may spend on a move.
+    #[clap(long)]
+    time_budget: Option<u64>,
+    /// A learned pattern table to evaluate penguin neighborhoods with.
+    #[clap(long)]
+    patterns: Option<String>,
+    /// A directory to record a replay of each game into.
+    #[clap(long)]
+    replay_dir: Option<String>,
+    /// A CSV file to append evaluation features of each game to.
+    #[clap(long)]
+    game_data: Option<String>,
+    /// A file to append pattern training samples of each game to.
+    #[clap(long)]
+    pattern_samples: Option<String>,
+    /// A file to append statistics of each move and game to, as JSON Lines.
+    #[clap(long)]
+    telemetry: Option<String>,
+    /// Records nothing, even if the config file asks for it.
+    #[clap(long, conflicts_with_all = &["replay-dir", "game-data", "pattern-samples", "telemetry"])]
+    no_recording: bool,
+    /// The number of attempts to reconnect if the connection drops during a reserved game, 0 to give up immediately.
+    #[clap(long)]
+    reconnect: Option<u32>,
+    /// The time in milliseconds after which a fallback move is sent in place of the strategy's.
+    #[clap(long)]
+    move_timeout: Option<u64>,
+}
+
+impl Args {
+    /// Overrides the settings of the config with the given arguments.
+    fn apply(self, config: &mut Config) {
+        if let Some(host) = self.host { config.host = host; }
+        if let Some(port) = self.port { config.port = port; }
+        if let Some(reservation) = self.reservation {
+            config.reservation = Some(reservation);
+            config.room = None;
+        }
+        if let Some(room) = self.room {
+            config.room = Some(room);
+            config.reservation = None;
+        }
+        if let Some(games) = self.games { config.games = games; }
+        if let Some(level) = self.level { config.level = level; }
+        if let Some(strategy) = self.strategy { config.strategy = strategy; }
+        if let Some(millis) = self.time_budget { config.time_budget = Duration::from_millis(millis); }
+        if let Some(patterns) = self.patterns { config.patterns = Some(patterns.into()); }
+        if let Some(replay_dir) = self.replay_dir { config.replay_dir = Some(replay_dir.into()); }
+        if let Some(game_data) = self.game_data { config.game_data = Some(game_data.into()); }
+        if let Some(pattern_samples) = self.pattern_samples { config.pattern_samples = Some(pattern_samples.into()); }
+        if let Some(telemetry) = self.telemetry { config.telemetry = Some(telemetry.into()); }
+        if self.no_recording {
+            config.replay_dir = None;
+            config.game_data = None;
+            config.pattern_samples = None;
+            config.telemetry = None;
+        }
+        if let Some(reconnect) = self.reconnect { config.reconnect = reconnect; }
+        if let Some(millis) = self.move_timeout { config.move_timeout = Duration::from_millis(millis); }
+    }
 }
-const filename:&str  ="gamedata_new_new_2.csv";
 
 fn main() {
-    // Parse command line arguments
+    // Parse command line arguments on top of the config file
     let args = Args::parse();
-    
-    // Set up logging
-    SimpleLogger::init(LevelFilter::from_str(&args.level).expect("Invalid log level."), Config::default()).expect("Could not initialize logger.");
-    
-    // Setup the client and the delegate
+    if args.strategies {
+        print_strategies();
+        return;
+    }
+    let mut config = match &args.config {
+        Some(path) => Config::load(path).unwrap_or_else(|e| {
+            eprintln!("Invalid config {}: {}", path, e);
+            process::exit(2);
+        }),
+        None => Config::default(),
+    };
     let debug_mode = DebugMode {
         debug_reader: args.debug_reader,
         debug_writer: args.debug_writer,
     };
-
-   // scoring_funcs::set_net(io::load("test.flow").unwrap());
+    args.apply(&mut config);
+    if let Err(e) = config.validate() {
+        eprintln!("Invalid configuration: {}", e);
+        process::exit(2);
+    }
+    
+    // Set up logging
+    SimpleLogger::init(config.level, LogConfig::default()).expect("Could not initialize logger.");
     
-    if !Path::new(&filename).exists() {
-        let mut file = OpenOptions::new()
-        .create_new(true)
-        .write(true)
-        .append(true)
-        .open(&filename)
-        .unwrap();
+   // scoring_funcs::set_net(io::load("test.flow").unwrap());
+    if let Some(path) = &config.patterns {
+        let table = PatternTable::load(path).unwrap_or_else(|e| {
+            eprintln!("Could not load pattern table {}: {}", path.display(), e);
+            process::exit(2);
+        });
+        patterns::set_table(table).expect("Could not set pattern table.");
     }
 
-    let client = GameClient::new(OwnLogic, debug_mode, args.reservation);
-    let _result = client.connect(&args.host, args.port).expect("Error while running client.");
+    // Setup the client and the delegate
+    let logic = OwnLogic::new(&config).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    info!("Playing with strategy {}", config.strategy);
+    let mut telemetry = Telemetry::new(logic).with_strategy(config.strategy.to_string());
+    if let Some(path) = &config.telemetry {
+        let file = OpenOptions::new().create(true).append(true).open(path).unwrap_or_else(|e| {
+            eprintln!("Could not open {}: {}", path.display(), e);
+            process::exit(2);
+        });
+        telemetry = telemetry.with_output(file);
+    }
+    let mut client = GameClient::new(telemetry, debug_mode, config.reservation)
+        .with_move_timeout(config.move_timeout);
+    if let Some(replay_dir) = config.replay_dir {
+        client = client.with_replay_dir(replay_dir);
+    }
+    if let Some(room) = config.room {
+        client = client.with_room_id(room);
+    }
+    client = client.with_games(Some(config.games).filter(|&g| g > 0));
+    if config.reconnect > 0 {
+        client = client.with_reconnect(Reconnect { attempts: config.reconnect, ..Reconnect::default() });
+    }
+    match client.connect(&config.host, config.port) {
+        Ok(results) => info!("Played {} game(s)", results.len()),
+        Err(e) => {
+            error!("Error while running client: {}", e);
+            process::exit(1);
+        },
+    }
+}
+
+/// Prints the registered strategies along with their options.
+fn print_strategies() {
+    for info in STRATEGIES {
+        println!("{:<10} {}", info.name, info.description);
+        for (key, description) in info.options {
+            println!("  {:<14} {}", key, description);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use clap::Parser;
+
+    use crate::config::Config;
+
+    use super::Args;
+
+    #[test]
+    fn test_arguments_override_config() {
+        let mut config: Config = "[connection]\nport = 1\nreservation = abc\n[engine]\ntime_budget_ms = 1000".parse().unwrap();
+        Args::parse_from(["client", "--port", "2", "--room", "x", "--strategy", "minimax:depth=2"]).apply(&mut config);
+        assert_eq!(config.port, 2);
+        assert_eq!(config.room.as_deref(), Some("x"));
+        assert_eq!(config.reservation, None);
+        assert_eq!(config.time_budget, Duration::from_millis(1000));
+        assert_eq!(config.strategy.to_string(), "minimax:depth=2");
+        assert!(Args::try_parse_from(["client", "--strategy", "minimax:width=2"]).is_err());
+    }
+
+    #[test]
+    fn test_recording_arguments() {
+        let mut config: Config = "[recording]\nreplay_dir = replays\ntelemetry = t.jsonl".parse().unwrap();
+        Args::parse_from(["client", "--game-data", "g.csv", "--pattern-samples", "p.csv", "--move-timeout", "900"]).apply(&mut config);
+        assert_eq!(config.game_data.as_deref(), Some(Path::new("g.csv")));
+        assert_eq!(config.pattern_samples.as_deref(), Some(Path::new("p.csv")));
+        assert_eq!(config.replay_dir.as_deref(), Some(Path::new("replays")));
+        assert_eq!(config.move_timeout, Duration::from_millis(900));
+
+        Args::parse_from(["client", "--no-recording"]).apply(&mut config);
+        assert_eq!((config.replay_dir, config.game_data, config.pattern_samples, config.telemetry), (None, None, None, None));
+        assert!(Args::try_parse_from(["client", "--no-recording", "--telemetry", "t.jsonl"]).is_err());
+    }
 }
@@ -1,38 +1,112 @@
-use std::{vec, thread, time::Duration, sync::mpsc::{self}, cmp::min};
+use std::{vec, thread, time::Duration, sync::mpsc::{self}, cmp::min, cell::{Cell, RefCell}};
 use log::info;
 
-use crate::{game::{State, Team, Move}, scoring_funcs::{ evaluate, fast_evaluate, get_fish_dif}};
+use crate::{game::{State, Team, Move}, scoring_funcs::{ evaluate, evaluate_quiet, fast_evaluate, get_fish_dif}, analysis::cut_moves_first, telemetry::SearchInfo};
 use std::time::Instant;
 
 const ZER_VEC:Vec<usize> = vec![];
 const BREAK_TIME:u128=1730;
 
+/// The default time budget of `dyn_max`, leaving a margin
+/// for the move to reach the server in time.
+pub const DEFAULT_BUDGET: Duration = Duration::from_millis(BREAK_TIME as u64);
+/// The default depth up to which `dyn_max` deepens.
+pub const DEFAULT_MAX_DEPTH: i32 = 30;
+/// Scores beyond this magnitude are decided games.
+pub const DECIDED: f32 = 1000.0;
+
+thread_local! {
+    /// The positions visited by searches on this thread since the last `take_nodes`.
+    static NODES: Cell<u64> = const { Cell::new(0) };
+    /// The best line found from each turn on, indexed by that turn.
+    static LINES: RefCell<Vec<Vec<Move>>> = const { RefCell::new(Vec::new()) };
+}
 
+/// Counts a visited position and clears its line,
+/// which stays empty if the position is a leaf.
+fn enter_node(turn: usize) {
+    NODES.with(|n| n.set(n.get() + 1));
+    LINES.with(|lines| {
+        let mut lines = lines.borrow_mut();
+        if lines.len() < turn + 2 {
+            lines.resize(turn + 2, Vec::new());
+        }
+        lines[turn].clear();
+    });
+}
+
+/// Makes the given move followed by the line of the
+/// following turn the best line from the given turn on.
+fn update_pv(turn: usize, m: Move) {
+    LINES.with(|lines| {
+        let mut lines = lines.borrow_mut();
+        let (line, rest) = lines.split_at_mut(turn + 1);
+        let line = &mut line[turn];
+        line.clear();
+        line.push(m);
+        line.extend_from_slice(&rest[0]);
+    });
+}
+
+/// Takes the number of positions the searches on this
+/// thread visited since the last call.
+pub fn take_nodes() -> u64 {
+    NODES.with(|n| n.replace(0))
+}
+
+/// The principal variation of the last search on this
+/// thread that started at the given turn.
+pub fn principal_variation(turn: usize) -> Vec<Move> {
+    LINES.with(|lines| lines.borrow().get(turn).cloned().unwrap_or_default())
+}
 
 
 pub fn dyn_max(gamestate:State, my_team:Team) -> Option<Move>
+{
+    dyn_max_within(gamestate, my_team, DEFAULT_BUDGET, DEFAULT_MAX_DEPTH)
+}
+
+/// Deepens the search iteratively until the time budget
+/// is used up or the maximum depth is reached.
+pub fn dyn_max_within(gamestate:State, my_team:Team, budget:Duration, max_depth:i32) -> Option<Move>
+{
+    dyn_max_search(gamestate, my_team, budget, max_depth).0
+}
+
+/// Like `dyn_max_within`, but also reports the depth, nodes,
+/// score and principal variation of the last finished iteration.
+pub fn dyn_max_search(gamestate:State, my_team:Team, budget:Duration, max_depth:i32) -> (Option<Move>, SearchInfo)
 {
     let start = Instant::now();
 
     let mut curmove:Option<Move>= None;
+    let mut info = SearchInfo::default();
     let mut controlfirst:Vec<usize> = (0..gamestate.possible_moves().len()).collect();
     let mut curdepth = 0;
 
-    while start.elapsed().as_millis() < BREAK_TIME && curdepth < 30 { 
+    while start.elapsed() < budget && curdepth < max_depth { 
         let (mtx, mrx) = mpsc::channel();
         let (stx, srx) = mpsc::channel();
         let mut cf = controlfirst.clone();
+        let depth = curdepth;
         thread::spawn(move || {
-            let (m, _, cf) = minimax2(&mut (gamestate.clone()), my_team, f32::MIN, f32::MAX, curdepth, 2, cf);
-            mtx.send(m);
+            let (m, score, cf) = minimax2(&mut (gamestate.clone()), my_team, f32::MIN, f32::MAX, depth, 2, cf);
+            mtx.send((m, score, take_nodes(), principal_variation(gamestate.turn())));
             stx.send(cf)
         });
-        while start.elapsed().as_millis() < BREAK_TIME {
+        while start.elapsed() < budget {
             let m = mrx.recv_timeout(Duration::new(0, 50));
             if !m.is_err() {
                 curdepth+=1;
-                curmove = m.unwrap();
-                if start.elapsed().as_millis() > BREAK_TIME {
+                let (m, score, nodes, pv) = m.unwrap();
+                curmove = m;
+                info.nodes += nodes;
+                if m.is_some() {
+                    info.depth = Some(depth as u32);
+                    info.score = Some(score);
+                    info.pv = pv;
+                }
+                if start.elapsed() > budget {
                     break;
                 }
                 controlfirst = srx.recv().unwrap();
@@ -41,7 +115,7 @@ pub fn dyn_max(gamestate:State, my_team:Team) -> Option<Move>
         }
     }
     info!("depth: {}", curdepth);
-    return curmove;
+    return (curmove, info);
 }
 
 
@@ -50,6 +124,8 @@ pub fn dyn_max(gamestate:State, my_team:Team) -> Option<Move>
 
 
 pub fn minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32, depth:i32,controlfirst:Vec<usize>) -> (Option<Move>, f32, Vec<usize>) {
+    let turn = gamestate.turn();
+    enter_node(turn);
     let mut my_turn = -1;
     if gamestate.current_team().index()== my_team.index() {
         my_turn = 1;
@@ -83,6 +159,7 @@ pub fn minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32,
                 gamestate.undo_move(m, f, my_team);
                 if  l > value {
                     best_move = m;
+                    update_pv(turn, best_move);
                     value = l;
                 }
                 alpha = f32::max(alpha, value);
@@ -100,6 +177,7 @@ pub fn minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32,
                 
                 if  l < value {
                     best_move = m;
+                    update_pv(turn, best_move);
                     value = l;
                 }
                 beta = f32::min(beta, value);
@@ -120,6 +198,7 @@ pub fn minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32,
             gamestate.undo_move(possible_moves[iter], f, my_team);
             if  l > value {
                 best_move = possible_moves[iter];
+                update_pv(turn, best_move);
                 value = l;
             }
             alpha = f32::max(alpha, value);
@@ -134,6 +213,8 @@ pub fn minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32,
 }
 
 pub fn minimax2(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32, depth:i32, depth2:i32,controlfirst:Vec<usize>) -> (Option<Move>, f32, Vec<usize>) {
+    let turn = gamestate.turn();
+    enter_node(turn);
     let mut my_turn = -1;
     if gamestate.current_team().index()== my_team.index() {
         my_turn = 1;
@@ -167,6 +248,7 @@ pub fn minimax2(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32,
                 gamestate.undo_move(m, f, my_team);
                 if  l > value {
                     best_move = m;
+                    update_pv(turn, best_move);
                     value = l;
                 }
                 alpha = f32::max(alpha, value);
@@ -184,6 +266,7 @@ pub fn minimax2(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32,
                 
                 if  l < value {
                     best_move = m;
+                    update_pv(turn, best_move);
                     value = l;
                 }
                 beta = f32::min(beta, value);
@@ -204,6 +287,7 @@ pub fn minimax2(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32,
             gamestate.undo_move(possible_moves[iter], f, my_team);
             if  l > value {
                 best_move = possible_moves[iter];
+                update_pv(turn, best_move);
                 value = l;
             }
             alpha = f32::max(alpha, value);
@@ -220,7 +304,15 @@ pub fn minimax2(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32,
 
 
 
-pub fn new_minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32, depth:i32, depth2:i32) -> (Option<Move>, f32) {
+pub fn new_minimax(gamestate:&mut State, my_team:Team, alpha:f32, beta:f32, depth:i32, depth2:i32) -> (Option<Move>, f32) {
+    new_minimax_with(gamestate, my_team, alpha, beta, depth, depth2, &|_, _| 0.0)
+}
+
+/// Like `new_minimax`, but adds the given term to the
+/// evaluation of the positions at the horizon.
+pub fn new_minimax_with(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f32, depth:i32, depth2:i32, term:&dyn Fn(&State, i32) -> f32) -> (Option<Move>, f32) {
+    let turn = gamestate.turn();
+    enter_node(turn);
     let mut my_turn = -1;
     if gamestate.current_team().index()== my_team.index() {
         my_turn = 1;
@@ -237,8 +329,17 @@ pub fn new_minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f
             return (None, f32::MIN+100.+get_fish_dif(gamestate, my_turn) as f32);
         };
     } 
-    if depth <= 0 {
-        return (None, evaluate(gamestate, my_turn));
+    // Search one ply deeper if a penguin fights over a small region,
+    // since the static evaluation cannot judge who wins that race.
+    // Checking this takes a search per penguin, so it is only done at
+    // depth 0, sharing the analysis with the evaluation.
+    if depth < 0 {
+        return (None, evaluate(gamestate, my_turn) + term(gamestate, my_turn));
+    }
+    if depth == 0 {
+        if let Some(score) = evaluate_quiet(gamestate, my_turn) {
+            return (None, score + term(gamestate, my_turn));
+        }
     }
 
     
@@ -248,6 +349,7 @@ pub fn new_minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f
         if my_turn==1 {
             possible_moves.reverse();
         }
+        cut_moves_first(gamestate.board(), &mut possible_moves);
     }
     let mut best_move =  possible_moves[0];
     let mut value;
@@ -255,10 +357,11 @@ pub fn new_minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f
             value = f32::MIN;
             for m in possible_moves {
                 let f = gamestate.perform(m);
-                let l = new_minimax(gamestate, my_team, alpha, beta, depth-1, depth2-1).1;
+                let l = new_minimax_with(gamestate, my_team, alpha, beta, depth-1, depth2-1, term).1;
                 gamestate.undo_move(m, f, my_team);
                 if  l > value {
                     best_move = m;
+                    update_pv(turn, best_move);
                     value = l;
                 }
                 alpha = f32::max(alpha, value);
@@ -271,11 +374,12 @@ pub fn new_minimax(gamestate:&mut State, my_team:Team, mut alpha:f32, mut beta:f
             value = f32::MAX;
             for m in possible_moves {
                 let f = gamestate.perform(m);
-                let l = new_minimax(gamestate, my_team, alpha, beta, depth-1, depth2-1).1;
+                let l = new_minimax_with(gamestate, my_team, alpha, beta, depth-1, depth2-1, term).1;
                 gamestate.undo_move(m, f, my_team.opponent());
                 
                 if  l < value {
                     best_move = m;
+                    update_pv(turn, best_move);
                     value = l;
                 }
                 beta = f32::min(beta, value);
@@ -0,0 +1,256 @@
+use std::{fmt, path::Path, str::FromStr, sync::OnceLock};
+
+use crate::{game::{Board, State, Team, Vec2, Doubled}, util::{Error, Result}};
+
+/// The number of neighbor configurations, one bit per hex direction.
+pub const NEIGHBOR_PATTERNS: usize = 1 << 6;
+
+/// The number of distinct floe counts on the second ring (0 to 12).
+pub const RING_COUNTS: usize = 13;
+
+/// Whether the field has fish on it (out-of-bounds fields do not).
+fn has_fish(board: &Board, coords: Vec2<Doubled>) -> bool {
+    board.get(coords).is_some_and(|f| f.fish() > 0)
+}
+
+/// Encodes which of the 6 neighbors of the given field are floes,
+/// with bit `i` corresponding to `Vec2::<Doubled>::DIRECTIONS[i]`.
+pub fn neighbor_mask(board: &Board, pos: Vec2<Doubled>) -> usize {
+    pos.hex_neighbors()
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, n)| has_fish(board, n))
+        .fold(0, |mask, (i, _)| mask | (1 << i))
+}
+
+/// Counts the floes at hex distance 2 from the given field.
+pub fn ring_count(board: &Board, pos: Vec2<Doubled>) -> usize {
+    // The second ring consists of the sums of two directions that
+    // neither cancel out nor form a single direction.
+    let directions = Vec2::<Doubled>::DIRECTIONS;
+    (0..directions.len())
+        .flat_map(|i| (i..directions.len()).map(move |j| directions[i] + directions[j]))
+        .filter(|&v| v != Vec2::ZERO && !directions.contains(&v))
+        .filter(|&v| has_fish(board, pos + v))
+        .count()
+}
+
+/// A lookup table of learned weights, indexed by the local
+/// configuration around a penguin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternTable {
+    /// Whether the floe count on the second ring is part of the pattern.
+    ring: bool,
+    weights: Vec<f32>,
+}
+
+impl PatternTable {
+    /// Creates a table with all weights set to zero.
+    pub fn new(ring: bool) -> Self {
+        let size = if ring { NEIGHBOR_PATTERNS * RING_COUNTS } else { NEIGHBOR_PATTERNS };
+        Self { ring, weights: vec![0.0; size] }
+    }
+
+    /// Whether the table includes the second ring.
+    pub fn ring(&self) -> bool { self.ring }
+
+    /// Fetches the weights.
+    pub fn weights(&self) -> &[f32] { &self.weights }
+
+    /// Fetches the weights mutably.
+    pub fn weights_mut(&mut self) -> &mut [f32] { &mut self.weights }
+
+    /// Computes the pattern index of the given field.
+    pub fn index(&self, board: &Board, pos: Vec2<Doubled>) -> usize {
+        let mask = neighbor_mask(board, pos);
+        if self.ring {
+            mask + NEIGHBOR_PATTERNS * ring_count(board, pos)
+        } else {
+            mask
+        }
+    }
+
+    /// The weight of the pattern around the given field.
+    pub fn score(&self, board: &Board, pos: Vec2<Doubled>) -> f32 {
+        self.weights[self.index(board, pos)]
+    }
+
+    /// The summed weights of the given team's penguins.
+    pub fn score_of(&self, board: &Board, team: Team) -> f32 {
+        board.penguins()
+            .filter(|&(_, t)| t == team)
+            .map(|(c, _)| self.score(board, c))
+            .sum()
+    }
+
+    /// The difference in pattern scores of the current team and the
+    /// opponent, from the perspective given by `my_turn`.
+    pub fn evaluate(&self, state: &State, my_turn: i32) -> f32 {
+        let team = state.current_team();
+        my_turn as f32 * (self.score_of(state.board(), team) - self.score_of(state.board(), team.opponent()))
+    }
+
+    /// Loads a table from the given file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+}
+
+impl fmt::Display for PatternTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", if self.ring { "ring" } else { "neighbors" })?;
+        for chunk in self.weights.chunks(NEIGHBOR_PATTERNS / 8) {
+            let line: Vec<_> = chunk.iter().map(|w| w.to_string()).collect();
+            writeln!(f, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PatternTable {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut lines = s.lines();
+        let ring = match lines.next().map(|l| l.trim()) {
+            Some("ring") => true,
+            Some("neighbors") => false,
+            header => return Err(Error::InvalidNotation { expected: "a pattern table header (ring or neighbors)", found: header.unwrap_or_default().to_owned() }),
+        };
+        let mut table = Self::new(ring);
+        let weights = lines
+            .flat_map(|l| l.split_whitespace())
+            .map(|w| Ok(w.parse::<f32>()?))
+            .collect::<Result<Vec<_>>>()?;
+        if weights.len() != table.weights.len() {
+            return Err(Error::WrongCount { what: "pattern weights", expected: table.weights.len(), found: weights.len() });
+        }
+        table.weights = weights;
+        Ok(table)
+    }
+}
+
+static TABLE: OnceLock<PatternTable> = OnceLock::new();
+
+/// Installs the table used by `get_pattern_scores`. Can only be set once.
+pub fn set_table(table: PatternTable) -> Result<()> {
+    TABLE.set(table).map_err(|_| Error::AlreadySet { what: "pattern table" })
+}
+
+/// Fetches the table installed with `set_table`, if any.
+pub fn installed_table() -> Option<&'static PatternTable> {
+    TABLE.get()
+}
+
+/// Scores the penguins' local patterns with the installed table, 0 without one.
+pub fn get_pattern_scores(gamestate: &State, my_turn: i32) -> f32 {
+    installed_table().map_or(0.0, |table| table.evaluate(gamestate, my_turn))
+}
+
+/// Encodes a training sample as a single line, consisting of the board
+/// rows separated by slashes and the result for team one (1 win, 0 draw, -1 loss).
+pub fn encode_sample(board: &Board, result: i32) -> String {
+    let rows: Vec<_> = board.to_string().lines().map(|l| l.to_owned()).collect();
+    format!("{};{}", rows.join("/"), result)
+}
+
+/// Decodes a training sample encoded by `encode_sample`.
+pub fn decode_sample(line: &str) -> Result<(Board, f32)> {
+    let (board, result) = line.trim().split_once(';')
+        .ok_or_else(|| Error::InvalidNotation { expected: "a pattern sample like <board>;<result>", found: line.to_owned() })?;
+    Ok((board.replace('/', "\n").parse()?, result.parse()?))
+}
+
+/// Fits the weights of a table to recorded positions using
+/// stochastic gradient descent on a tanh-squashed linear model.
+pub struct PatternLearner {
+    table: PatternTable,
+    rate: f32,
+}
+
+impl PatternLearner {
+    /// Creates a learner starting from an all-zero table.
+    pub fn new(ring: bool, rate: f32) -> Self {
+        Self { table: PatternTable::new(ring), rate }
+    }
+
+    /// Performs a gradient step on the given position and returns the squared error.
+    /// The result is from the perspective of team one, in the range -1 to 1.
+    pub fn train(&mut self, board: &Board, result: f32) -> f32 {
+        let features: Vec<_> = board.penguins()
+            .map(|(c, t)| (self.table.index(board, c), if t == Team::One { 1.0 } else { -1.0 }))
+            .collect();
+        let prediction = features.iter()
+            .map(|&(i, sign)| sign * self.table.weights[i])
+            .sum::<f32>()
+            .tanh();
+        let error = result - prediction;
+        let gradient = self.rate * error * (1.0 - prediction * prediction);
+        for (i, sign) in features {
+            self.table.weights[i] += gradient * sign;
+        }
+        error * error
+    }
+
+    /// Fetches the learned table.
+    pub fn table(&self) -> &PatternTable { &self.table }
+
+    /// Consumes the learner, returning the learned table.
+    pub fn into_table(self) -> PatternTable { self.table }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{game::{Board, Team, Vec2, Direct}, util::Error};
+
+    use super::{neighbor_mask, ring_count, PatternTable, PatternLearner, encode_sample, decode_sample};
+
+    fn board() -> Board {
+        indoc! {r#"
+            11100000
+            1R100000
+            11100000
+            00000000
+            00000000
+            00000000
+            00000000
+            0000000B
+        "#}.parse().unwrap()
+    }
+
+    #[test]
+    fn test_neighbor_mask() {
+        let board = board();
+        assert_eq!(neighbor_mask(&board, Vec2::<Direct>::new(1, 1).into()), 0b111111);
+        assert_eq!(neighbor_mask(&board, Vec2::<Direct>::new(7, 7).into()), 0);
+        assert_eq!(ring_count(&board, Vec2::<Direct>::new(1, 1).into()), 2);
+    }
+
+    #[test]
+    fn test_table_roundtrip() {
+        let mut table = PatternTable::new(true);
+        table.weights_mut()[3] = 0.5;
+        table.weights_mut()[100] = -1.25;
+        assert_eq!(table.to_string().parse::<PatternTable>().unwrap(), table);
+        assert_eq!("neighbors\n1 2 3".parse::<PatternTable>().unwrap_err().to_string(), "Expected 64 pattern weights, found 3");
+        assert!(matches!("squares\n1".parse::<PatternTable>(), Err(Error::InvalidNotation { .. })));
+        assert!(matches!(decode_sample("00000000"), Err(Error::InvalidNotation { .. })));
+    }
+
+    #[test]
+    fn test_learning() {
+        let board = board();
+        let line = encode_sample(&board, 1);
+        let (decoded, result) = decode_sample(&line).unwrap();
+        assert_eq!(decoded, board);
+
+        let mut learner = PatternLearner::new(false, 0.1);
+        for _ in 0..100 {
+            learner.train(&decoded, result);
+        }
+        let table = learner.into_table();
+        assert!(table.score_of(&board, Team::One) > table.score_of(&board, Team::Two));
+    }
+}
@@ -3,7 +3,7 @@ use crate::util::{Element, Result, Error};
 use super::EventPayload;
 
 /// A message from the server.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Event {
     /// Notifies the client that they successfully joined a room.
     Joined { room_id: String },
@@ -11,6 +11,13 @@ pub enum Event {
     Left { room_id: String },
     /// A message in a room.
     Room { room_id: String, payload: EventPayload },
+    /// Notifies an administrator that a game was prepared,
+    /// with a reservation code for each slot.
+    Prepared { room_id: String, reservations: Vec<String> },
+    /// Notifies an administrator that they observe a room.
+    Observed { room_id: String },
+    /// Notifies an administrator that a room was created.
+    RoomCreated { room_id: String },
 }
 
 impl TryFrom<&Element> for Event {
@@ -24,7 +31,121 @@ impl TryFrom<&Element> for Event {
                 room_id: elem.attribute("roomId")?.to_owned(),
                 payload: elem.child_by_name("data")?.try_into()?,
             }),
+            "prepared" => Ok(Self::Prepared {
+                room_id: elem.attribute("roomId")?.to_owned(),
+                reservations: elem.childs_by_name("reservation").map(|r| r.content().to_owned()).collect(),
+            }),
+            "observed" => Ok(Self::Observed { room_id: elem.attribute("roomId")?.to_owned() }),
+            "roomCreated" => Ok(Self::RoomCreated { room_id: elem.attribute("roomId")?.to_owned() }),
+            "errorpacket" => Err(Error::ServerError {
+                message: elem.attribute("message")?.to_owned(),
+                request: elem.child_by_name("originalRequest").ok().cloned().map(Box::new),
+            }),
             _ => Err(Error::UnknownElement(elem.clone())),
         }
     }
 }
+
+impl From<Event> for Element {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::Joined { room_id } => Element::new("joined").attribute("roomId", room_id).build(),
+            Event::Left { room_id } => Element::new("left").attribute("roomId", room_id).build(),
+            Event::Room { room_id, payload } => Element::new("room")
+                .attribute("roomId", room_id)
+                .child(payload)
+                .build(),
+            Event::Prepared { room_id, reservations } => Element::new("prepared")
+                .attribute("roomId", room_id)
+                .childs(reservations.iter().map(|r| Element::new("reservation").content(r).build()))
+                .build(),
+            Event::Observed { room_id } => Element::new("observed").attribute("roomId", room_id).build(),
+            Event::RoomCreated { room_id } => Element::new("roomCreated").attribute("roomId", room_id).build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use indoc::indoc;
+
+    use crate::{util::{Element, Error}, protocol::{Event, EventPayload, GameResult, Player, Score, ScoreAggregation, ScoreCause, ScoreDefinition, ScoreDefinitionFragment}, game::{State, Team}, hashmap};
+
+    fn parse(xml: &str) -> Event {
+        Event::try_from(&Element::from_str(xml).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_admin_events_from_xml() {
+        match parse(indoc! {r#"
+            <prepared roomId="abc">
+                <reservation>r1</reservation>
+                <reservation>r2</reservation>
+            </prepared>
+        "#}) {
+            Event::Prepared { room_id, reservations } => {
+                assert_eq!(room_id, "abc");
+                assert_eq!(reservations, vec!["r1", "r2"]);
+            },
+            e => panic!("Unexpected event {:?}", e),
+        }
+        assert!(matches!(parse(r#"<observed roomId="abc" />"#), Event::Observed { room_id } if room_id == "abc"));
+        assert!(matches!(parse(r#"<roomCreated roomId="abc" />"#), Event::RoomCreated { room_id } if room_id == "abc"));
+    }
+
+    #[test]
+    fn test_server_errors() {
+        let room_error = Element::from_str(indoc! {r#"
+            <room roomId="abc">
+                <data class="error" message="Invalid move">
+                    <originalMessage class="move">
+                        <to x="3" y="1"/>
+                    </originalMessage>
+                </data>
+            </room>
+        "#}).unwrap();
+        match Event::try_from(&room_error) {
+            Err(Error::ServerError { message, request: Some(request) }) => {
+                assert_eq!(message, "Invalid move");
+                assert_eq!(request.attribute("class").unwrap(), "move");
+            },
+            e => panic!("Unexpected result {:?}", e),
+        }
+
+        let packet = Element::from_str(r#"<errorpacket message="Unknown room"><originalRequest class="joinRoom" roomId="x"/></errorpacket>"#).unwrap();
+        let error = Event::try_from(&packet).unwrap_err();
+        assert!(matches!(error, Error::ServerError { request: Some(_), .. }));
+        assert!(error.to_string().starts_with("Server error: Unknown room (caused by <originalRequest"));
+    }
+
+    #[test]
+    fn test_xml_roundtrip() {
+        let state: State = "R1000000/02000000/00000000/00000000/00000000/00000000/00000000/0000000B B 2 1:1 h8".parse().unwrap();
+        let result = GameResult::new(
+            ScoreDefinition::new([ScoreDefinitionFragment::new("Siegpunkte", ScoreAggregation::Sum, true)]),
+            hashmap![
+                Player::new(Some("rad"), Team::One) => Score::new(ScoreCause::Regular, "", [2]),
+                Player::new(Some("blues"), Team::Two) => Score::new(ScoreCause::Left, "Player left", [0])
+            ],
+            Some(Player::new(None, Team::One))
+        );
+        let room = |payload| Event::Room { room_id: "abc".to_owned(), payload };
+        for event in [
+            Event::Joined { room_id: "abc".to_owned() },
+            Event::Left { room_id: "abc".to_owned() },
+            room(EventPayload::Welcome(Team::Two)),
+            room(EventPayload::Memento(state)),
+            room(EventPayload::MoveRequest),
+            room(EventPayload::GameResult(result)),
+            Event::Prepared { room_id: "abc".to_owned(), reservations: vec!["r1".to_owned(), "r2".to_owned()] },
+            Event::Observed { room_id: "abc".to_owned() },
+            Event::RoomCreated { room_id: "abc".to_owned() },
+        ] {
+            let xml = Element::from(event.clone());
+            assert_eq!(Event::try_from(&xml).unwrap(), event);
+            assert_eq!(parse(&xml.to_string()), event);
+        }
+    }
+}
@@ -5,7 +5,7 @@ use crate::{util::{Element, Result, Error}, game::{State, Team}};
 use super::GameResult;
 
 /// The data of a room message from the server.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EventPayload {
     /// A welcome message by the server.
     Welcome(Team),
@@ -37,12 +37,34 @@ impl TryFrom<&Element> for EventPayload {
 
     fn try_from(elem: &Element) -> Result<Self> {
         match elem.attribute("class")? {
-            "welcomeMessage" => Ok(Self::Welcome(elem.attribute("color")?.parse()?)),
+            "welcomeMessage" => Ok(Self::Welcome(elem.parse_attribute("color")?)),
             "memento" => Ok(Self::Memento(elem.child_by_name("state")?.try_into()?)),
             "moveRequest" => Ok(Self::MoveRequest),
             "result" => Ok(Self::GameResult(elem.try_into()?)),
-            "error" => Err(Error::ServerError(elem.attribute("message")?.to_owned())),
+            "error" => Err(Error::ServerError {
+                message: elem.attribute("message")?.to_owned(),
+                request: elem.child_by_name("originalMessage").ok().cloned().map(Box::new),
+            }),
             _ => Err(Error::UnknownElement(elem.clone())),
         }
     }
 }
+
+impl From<EventPayload> for Element {
+    fn from(payload: EventPayload) -> Self {
+        match payload {
+            EventPayload::Welcome(team) => Element::new("data")
+                .attribute("class", "welcomeMessage")
+                .attribute("color", team)
+                .build(),
+            EventPayload::Memento(state) => Element::new("data")
+                .attribute("class", "memento")
+                .child(state)
+                .build(),
+            EventPayload::MoveRequest => Element::new("data")
+                .attribute("class", "moveRequest")
+                .build(),
+            EventPayload::GameResult(result) => result.into(),
+        }
+    }
+}
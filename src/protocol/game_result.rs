@@ -47,11 +47,36 @@ impl TryFrom<&Element> for GameResult {
     }
 }
 
+impl From<GameResult> for Element {
+    fn from(result: GameResult) -> Self {
+        // Order the entries by team to keep the output stable
+        let mut scores: Vec<_> = result.scores.into_iter().collect();
+        scores.sort_by_key(|(player, _)| player.team().index());
+        Element::new("data")
+            .attribute("class", "result")
+            .child(result.definition)
+            .child(Element::new("scores")
+                .childs(scores.into_iter().map(|(player, score)| Element::new("entry")
+                    .child(player)
+                    .child(score)
+                    .build())))
+            .option_child(result.winner.map(|w| {
+                let mut builder = Element::new("winner");
+                if let Some(name) = w.name() {
+                    builder = builder.attribute("name", name);
+                }
+                builder.attribute("team", w.team()).build()
+            }))
+            .build()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{collections::HashMap, str::FromStr};
 
     use indoc::indoc;
+    use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 
     use crate::{util::Element, protocol::{ScoreDefinition, ScoreDefinitionFragment, ScoreAggregation, GameResult, Player, Score, ScoreCause}, game::Team, hashmap};
 
@@ -99,4 +124,33 @@ mod tests {
             Some(Player::new(None, Team::One))
         ));
     }
+
+    fn random_name(rng: &mut impl Rng) -> String {
+        const CHARS: &[char] = &['a', 'Z', '7', ' ', '∅', '<', '&', '"'];
+        (0..rng.gen_range(1..10)).map(|_| *CHARS.choose(rng).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_xml_roundtrip() {
+        const CAUSES: [ScoreCause; 6] = [ScoreCause::Regular, ScoreCause::Left, ScoreCause::RuleViolation, ScoreCause::SoftTimeout, ScoreCause::HardTimeout, ScoreCause::Unknown];
+        let mut rng = StdRng::seed_from_u64(43);
+        for _ in 0..100 {
+            let definition = ScoreDefinition::new((0..rng.gen_range(0..3)).map(|_| ScoreDefinitionFragment::new(
+                &random_name(&mut rng),
+                if rng.gen() { ScoreAggregation::Sum } else { ScoreAggregation::Average },
+                rng.gen(),
+            )).collect::<Vec<_>>());
+            let players = [Team::One, Team::Two].map(|team| Player::new(rng.gen::<bool>().then(|| random_name(&mut rng)).as_deref(), team));
+            let scores = players.iter().cloned().map(|p| {
+                let parts: Vec<i32> = (0..rng.gen_range(0..3)).map(|_| rng.gen_range(-100..100)).collect();
+                (p, Score::new(*CAUSES.choose(&mut rng).unwrap(), &random_name(&mut rng), parts))
+            }).collect::<HashMap<_, _>>();
+            let winner = players.choose(&mut rng).filter(|_| rng.gen()).cloned();
+            let result = GameResult::new(definition, scores, winner);
+
+            let xml = Element::from(result.clone());
+            assert_eq!(GameResult::try_from(&xml).unwrap(), result);
+            assert_eq!(GameResult::try_from(&xml.to_string().parse::<Element>().unwrap()).unwrap(), result);
+        }
+    }
 }
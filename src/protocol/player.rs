@@ -25,11 +25,21 @@ impl TryFrom<&Element> for Player {
     fn try_from(elem: &Element) -> Result<Self> {
         Ok(Player {
             name: elem.attribute("name").ok().map(|s| s.to_owned()),
-            team: elem.attribute("team")?.parse()?,
+            team: elem.parse_attribute("team")?,
         })
     }
 }
 
+impl From<Player> for Element {
+    fn from(player: Player) -> Self {
+        let mut builder = Element::new("player");
+        if let Some(name) = player.name {
+            builder = builder.attribute("name", name);
+        }
+        builder.attribute("team", player.team).build()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -1,11 +1,11 @@
-use crate::util::Element;
+use crate::util::{Element, Error, Result};
 
-use super::RequestPayload;
+use super::{RequestPayload, Slot};
 
-const GAME_TYPE: &str = "swc_2023_penguins";
+pub(crate) const GAME_TYPE: &str = "swc_2023_penguins";
 
 /// A message from the client.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Request {
     /// Joins an abitrary open game.
     Join,
@@ -16,6 +16,19 @@ pub enum Request {
     JoinPrepared { reservation_code: String },
     /// A message in a room.
     Room { room_id: String, payload: RequestPayload },
+    /// Authenticates as an administrator.
+    Authenticate { password: String },
+    /// Prepares a game with the given player slots (administrators only).
+    /// The server answers with the room id and reservation codes.
+    Prepare { slots: Vec<Slot>, pause: bool },
+    /// Observes the game in the given room (administrators only).
+    Observe { room_id: String },
+    /// Pauses or unpauses the game in the given room (administrators only).
+    Pause { room_id: String, pause: bool },
+    /// Advances a paused game by one move (administrators only).
+    Step { room_id: String },
+    /// Cancels the game in the given room (administrators only).
+    Cancel { room_id: String },
 }
 
 impl From<Request> for Element {
@@ -25,6 +38,82 @@ impl From<Request> for Element {
             Request::JoinRoom { room_id } => Element::new("joinRoom").attribute("roomId", room_id).build(),
             Request::JoinPrepared { reservation_code } => Element::new("joinPrepared").attribute("reservationCode", reservation_code).build(),
             Request::Room { room_id, payload } => Element::new("room").attribute("roomId", room_id).child(payload).build(),
+            Request::Authenticate { password } => Element::new("authenticate").attribute("password", password).build(),
+            Request::Prepare { slots, pause } => Element::new("prepare")
+                .attribute("gameType", GAME_TYPE)
+                .attribute("pause", pause)
+                .childs(slots.into_iter().map(Element::from))
+                .build(),
+            Request::Observe { room_id } => Element::new("observe").attribute("roomId", room_id).build(),
+            Request::Pause { room_id, pause } => Element::new("pause").attribute("roomId", room_id).attribute("pause", pause).build(),
+            Request::Step { room_id } => Element::new("step").attribute("roomId", room_id).build(),
+            Request::Cancel { room_id } => Element::new("cancel").attribute("roomId", room_id).build(),
+        }
+    }
+}
+
+impl TryFrom<&Element> for Request {
+    type Error = Error;
+
+    fn try_from(elem: &Element) -> Result<Self> {
+        let room_id = || -> Result<String> { Ok(elem.attribute("roomId")?.to_owned()) };
+        match elem.name() {
+            "join" => Ok(Self::Join),
+            "joinRoom" => Ok(Self::JoinRoom { room_id: room_id()? }),
+            "joinPrepared" => Ok(Self::JoinPrepared { reservation_code: elem.attribute("reservationCode")?.to_owned() }),
+            "room" => Ok(Self::Room { room_id: room_id()?, payload: elem.child_by_name("data")?.try_into()? }),
+            "authenticate" => Ok(Self::Authenticate { password: elem.attribute("password")?.to_owned() }),
+            "prepare" => Ok(Self::Prepare {
+                slots: elem.childs_by_name("slot").map(Slot::try_from).collect::<Result<_>>()?,
+                pause: elem.parse_attribute("pause")?,
+            }),
+            "observe" => Ok(Self::Observe { room_id: room_id()? }),
+            "pause" => Ok(Self::Pause { room_id: room_id()?, pause: elem.parse_attribute("pause")? }),
+            "step" => Ok(Self::Step { room_id: room_id()? }),
+            "cancel" => Ok(Self::Cancel { room_id: room_id()? }),
+            _ => Err(Error::UnknownElement(elem.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use indoc::indoc;
+
+    use crate::{util::Element, protocol::{Request, RequestPayload, Slot}, game::{Move, Vec2, Doubled}};
+
+    #[test]
+    fn test_prepare_to_xml() {
+        assert_eq!(Element::from(Request::Prepare {
+            slots: vec![Slot::new("One", true, true), Slot::new("Two", false, true)],
+            pause: true,
+        }), Element::from_str(indoc! {r#"
+            <prepare gameType="swc_2023_penguins" pause="true">
+                <slot displayName="One" canTimeout="true" reserved="true" />
+                <slot displayName="Two" canTimeout="false" reserved="true" />
+            </prepare>
+        "#}).unwrap());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let requests = [
+            Request::Join,
+            Request::JoinRoom { room_id: "abc".to_owned() },
+            Request::JoinPrepared { reservation_code: "xyz".to_owned() },
+            Request::Room { room_id: "abc".to_owned(), payload: RequestPayload::Move(Move::placing(Vec2::<Doubled>::new(3, 1))) },
+            Request::Authenticate { password: "secret".to_owned() },
+            Request::Prepare { slots: vec![Slot::new("One", true, true)], pause: false },
+            Request::Observe { room_id: "abc".to_owned() },
+            Request::Pause { room_id: "abc".to_owned(), pause: false },
+            Request::Step { room_id: "abc".to_owned() },
+            Request::Cancel { room_id: "abc".to_owned() },
+        ];
+        for request in requests {
+            let xml = Element::from(request.clone()).to_string();
+            assert_eq!(Request::try_from(&Element::from_str(&xml).unwrap()).unwrap(), request);
         }
     }
 }
@@ -1,7 +1,7 @@
-use crate::{util::Element, game::Move};
+use crate::{util::{Element, Error, Result}, game::Move};
 
 /// The data of a room message to the server.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RequestPayload {
     /// A move to be performed.
     Move(Move),
@@ -14,3 +14,14 @@ impl From<RequestPayload> for Element {
         }
     }
 }
+
+impl TryFrom<&Element> for RequestPayload {
+    type Error = Error;
+
+    fn try_from(elem: &Element) -> Result<Self> {
+        match elem.attribute("class")? {
+            "move" => Ok(Self::Move(elem.try_into()?)),
+            _ => Err(Error::UnknownElement(elem.clone())),
+        }
+    }
+}
@@ -30,13 +30,24 @@ impl TryFrom<&Element> for Score {
 
     fn try_from(elem: &Element) -> Result<Self> {
         Ok(Score {
-            cause: elem.attribute("cause")?.parse()?,
+            cause: elem.parse_attribute("cause")?,
             reason: elem.attribute("reason")?.to_owned(),
             parts: elem.childs_by_name("part").map(|p| Ok(p.content().parse::<i32>()?)).collect::<Result<_>>()?,
         })
     }
 }
 
+impl From<Score> for Element {
+    fn from(score: Score) -> Self {
+        let parts: Vec<String> = score.parts.iter().map(|p| p.to_string()).collect();
+        Element::new("score")
+            .attribute("cause", score.cause)
+            .attribute("reason", score.reason)
+            .childs(parts.iter().map(|p| Element::new("part").content(p).build()))
+            .build()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -24,7 +24,7 @@ impl FromStr for ScoreAggregation {
         match s {
             "SUM" => Ok(Self::Sum),
             "AVERAGE" => Ok(Self::Average),
-            _ => Err(Error::UnknownVariant(format!("Unknown aggregation {}", s))),
+            _ => Err(Error::UnknownVariant { kind: "aggregation", found: s.to_owned() }),
         }
     }
 }
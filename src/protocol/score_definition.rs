@@ -26,6 +26,14 @@ impl TryFrom<&Element> for ScoreDefinition {
     }
 }
 
+impl From<ScoreDefinition> for Element {
+    fn from(definition: ScoreDefinition) -> Self {
+        Element::new("definition")
+            .childs(definition.fragments.into_iter().map(Element::from))
+            .build()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
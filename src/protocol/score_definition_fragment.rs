@@ -35,3 +35,15 @@ impl TryFrom<&Element> for ScoreDefinitionFragment {
         })
     }
 }
+
+impl From<ScoreDefinitionFragment> for Element {
+    fn from(fragment: ScoreDefinitionFragment) -> Self {
+        let aggregation = fragment.aggregation.to_string();
+        let relevant_for_ranking = fragment.relevant_for_ranking.to_string();
+        Element::new("fragment")
+            .attribute("name", fragment.name)
+            .child(Element::new("aggregation").content(&aggregation))
+            .child(Element::new("relevantForRanking").content(&relevant_for_ranking))
+            .build()
+    }
+}
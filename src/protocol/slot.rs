@@ -0,0 +1,68 @@
+use crate::util::{Element, Error, Result};
+
+/// A player slot in a game prepared by an administrator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    display_name: String,
+    can_timeout: bool,
+    reserved: bool,
+}
+
+impl Slot {
+    #[inline]
+    pub fn new(display_name: &str, can_timeout: bool, reserved: bool) -> Self {
+        Self { display_name: display_name.to_owned(), can_timeout, reserved }
+    }
+
+    #[inline]
+    pub fn display_name(&self) -> &str { self.display_name.as_str() }
+
+    /// Whether the player in this slot may lose by exceeding the time limit.
+    #[inline]
+    pub fn can_timeout(&self) -> bool { self.can_timeout }
+
+    /// Whether the slot can only be joined with a reservation code.
+    #[inline]
+    pub fn reserved(&self) -> bool { self.reserved }
+}
+
+impl From<Slot> for Element {
+    fn from(slot: Slot) -> Self {
+        Element::new("slot")
+            .attribute("displayName", slot.display_name)
+            .attribute("canTimeout", slot.can_timeout)
+            .attribute("reserved", slot.reserved)
+            .build()
+    }
+}
+
+impl TryFrom<&Element> for Slot {
+    type Error = Error;
+
+    fn try_from(elem: &Element) -> Result<Self> {
+        Ok(Slot {
+            display_name: elem.attribute("displayName")?.to_owned(),
+            can_timeout: elem.parse_attribute("canTimeout")?,
+            reserved: elem.parse_attribute("reserved")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use indoc::indoc;
+
+    use crate::{util::Element, protocol::Slot};
+
+    #[test]
+    fn test_xml() {
+        let slot = Slot::new("Alice", true, false);
+        let xml = Element::from_str(indoc! {r#"
+            <slot displayName="Alice" canTimeout="true" reserved="false" />
+        "#}).unwrap();
+        assert_eq!(Slot::try_from(&xml).unwrap(), slot);
+        assert_eq!(Element::from(slot), xml);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::analysis::{FieldSet, Reachability};
+use crate::game::{Board, Move, State, Team, Vec2, Doubled, BOARD_SIZE, TEAMS};
+
+/// The coordinate labels drawn around the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Labels {
+    /// No labels.
+    None,
+    /// Column letters and row numbers as in the move notation.
+    #[default]
+    Squares,
+    /// Direct coordinates, i.e. the index of the field in its row.
+    Direct,
+    /// Doubled coordinates, with the columns of even rows
+    /// above and those of odd rows below the board.
+    Doubled,
+}
+
+/// Draws boards as text with staggered hex rows, optionally using
+/// ANSI colors and highlighting fields of interest.
+///
+/// Without colors, the last move is marked as `[3]`, legal targets as
+/// `<3>` and territory by the lowercase letter of its team (`3r`).
+#[derive(Debug, Clone, Default)]
+pub struct Renderer {
+    labels: Labels,
+    color: bool,
+    last_move: Option<Move>,
+    targets: FieldSet,
+    territory: [FieldSet; TEAMS],
+}
+
+/// The width of a field including the separating space.
+const CELL_WIDTH: usize = 4;
+/// The width of the row labels.
+const LABEL_WIDTH: usize = 3;
+
+impl Renderer {
+    /// Creates a renderer with square labels, without colors and highlights.
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets the coordinate labels.
+    pub fn labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Enables or disables ANSI colors.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Highlights the fields of the given move.
+    pub fn last_move(mut self, last_move: Option<Move>) -> Self {
+        self.last_move = last_move;
+        self
+    }
+
+    /// Highlights the given fields as move targets.
+    pub fn targets(mut self, targets: FieldSet) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Highlights the given fields as territory of the team.
+    pub fn territory(mut self, team: Team, fields: FieldSet) -> Self {
+        self.territory[team.index()] = fields;
+        self
+    }
+
+    /// Highlights the last move and the targets of the legal moves in the state.
+    pub fn highlight_state(self, state: &State) -> Self {
+        let targets = state.possible_moves().into_iter().map(|m| m.to()).collect();
+        self.last_move(state.last_move()).targets(targets)
+    }
+
+    /// Highlights the fields each team reaches strictly first.
+    pub fn highlight_territory(mut self, board: &Board) -> Self {
+        let voronoi = Reachability::of(board).voronoi(board);
+        self.territory = voronoi.owned;
+        self
+    }
+
+    /// Draws the board.
+    pub fn render(&self, board: &Board) -> String {
+        let mut out = String::new();
+        match self.labels {
+            Labels::None => {},
+            Labels::Squares => out += &self.column_labels(0, |x| ((b'a' + x as u8) as char).to_string()),
+            Labels::Direct => out += &self.column_labels(0, |x| x.to_string()),
+            Labels::Doubled => out += &self.column_labels(0, |x| (2 * x).to_string()),
+        }
+        for y in 0..BOARD_SIZE {
+            let mut line = match self.labels {
+                Labels::None => String::new(),
+                Labels::Squares => format!("{:>2} ", y + 1),
+                Labels::Direct | Labels::Doubled => format!("{:>2} ", y),
+            };
+            if y % 2 == 1 {
+                line += &" ".repeat(CELL_WIDTH / 2);
+            }
+            for x in 0..BOARD_SIZE {
+                line += &self.cell(board, Vec2::new((2 * x + y % 2) as i32, y as i32));
+                line += " ";
+            }
+            out += line.trim_end();
+            out += "\n";
+        }
+        if self.labels == Labels::Doubled {
+            out += &self.column_labels(CELL_WIDTH / 2, |x| (2 * x + 1).to_string());
+        }
+        out
+    }
+
+    fn column_labels(&self, offset: usize, label: impl Fn(usize) -> String) -> String {
+        let mut line = " ".repeat(LABEL_WIDTH + offset);
+        for x in 0..BOARD_SIZE {
+            line += &format!(" {:<width$}", label(x), width = CELL_WIDTH - 1);
+        }
+        line.trim_end().to_owned() + "\n"
+    }
+
+    fn cell(&self, board: &Board, coords: Vec2<Doubled>) -> String {
+        let field = board[coords];
+        let symbol = match field.penguin() {
+            Some(team) => team.letter().to_string(),
+            None if field.fish() == 0 => ".".to_owned(),
+            None => field.fish().to_string(),
+        };
+        let last = self.last_move.is_some_and(|m| m.to() == coords || m.from() == Some(coords));
+        let target = self.targets.contains(coords);
+        let owner = [Team::One, Team::Two].into_iter().find(|t| self.territory[t.index()].contains(coords));
+
+        if !self.color {
+            return if last {
+                format!("[{}]", symbol)
+            } else if target {
+                format!("<{}>", symbol)
+            } else if let Some(owner) = owner {
+                format!(" {}{}", symbol, owner.letter().to_ascii_lowercase())
+            } else {
+                format!(" {} ", symbol)
+            };
+        }
+
+        let mut codes = Vec::new();
+        match field.penguin() {
+            Some(Team::One) => codes.push("1;31"),
+            Some(Team::Two) => codes.push("1;34"),
+            None if field.fish() == 0 => codes.push("2"),
+            None => {},
+        }
+        if target {
+            codes.push("4;32");
+        }
+        match owner {
+            Some(Team::One) => codes.push("41"),
+            Some(Team::Two) => codes.push("44"),
+            None => {},
+        }
+        if last {
+            codes.push("7");
+        }
+        if codes.is_empty() {
+            format!(" {} ", symbol)
+        } else {
+            format!("\x1b[{}m {} \x1b[0m", codes.join(";"), symbol)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{analysis::FieldSet, game::{Board, Move, Team, Vec2, Doubled}};
+
+    use super::{Labels, Renderer};
+
+    fn board() -> Board {
+        indoc! {r#"
+            R1000000
+            02000000
+            00000000
+            00000000
+            00000000
+            00000000
+            00000000
+            0000000B
+        "#}.parse().unwrap()
+    }
+
+    #[test]
+    fn test_render_labels() {
+        let direct = Renderer::new().labels(Labels::Direct).render(&board());
+        let lines: Vec<_> = direct.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "    0   1   2   3   4   5   6   7");
+        assert_eq!(lines[1], " 0  R   1   .   .   .   .   .   .");
+        assert_eq!(lines[2], " 1    .   2   .   .   .   .   .   .");
+        assert_eq!(lines[8], " 7    .   .   .   .   .   .   .   B");
+        let doubled = Renderer::new().labels(Labels::Doubled).render(&board());
+        let lines: Vec<_> = doubled.lines().collect();
+        assert_eq!(lines[0], "    0   2   4   6   8   10  12  14");
+        assert_eq!(lines[9], "      1   3   5   7   9   11  13  15");
+        assert!(Renderer::new().labels(Labels::Squares).render(&board()).starts_with("    a   b"));
+        assert_eq!(Renderer::new().labels(Labels::None).render(&board()).lines().next(), Some(" R   1   .   .   .   .   .   ."));
+    }
+
+    #[test]
+    fn test_render_highlights() {
+        let rendered = Renderer::new()
+            .labels(Labels::None)
+            .last_move(Some(Move::placing(Vec2::<Doubled>::new(0, 0))))
+            .targets([Vec2::<Doubled>::new(2, 0)].into_iter().collect())
+            .territory(Team::Two, [Vec2::<Doubled>::new(3, 1)].into_iter().collect())
+            .render(&board());
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(lines[0], "[R] <1>  .   .   .   .   .   .");
+        assert_eq!(lines[1], "   .   2b  .   .   .   .   .   .");
+
+        let colored = Renderer::new().color(true).targets(FieldSet::EMPTY).render(&board());
+        assert!(colored.contains("\x1b[1;31m R \x1b[0m"));
+        assert!(colored.contains("\x1b[1;34m B \x1b[0m"));
+    }
+}
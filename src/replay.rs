@@ -0,0 +1,320 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use quick_xml::Writer;
+use crate::game::{State, Team, Move};
+use crate::protocol::GameResult;
+use crate::util::{Element, Error, Result};
+
+/// A single recorded event of a game.
+#[derive(Debug, Clone)]
+pub enum ReplayEntry {
+    /// A game state received from the server, with the
+    /// time since the start of the recording.
+    State { state: Box<State>, elapsed: Duration },
+    /// A move sent to the server, with the time the delegate took.
+    Move { turn: usize, m: Move, duration: Duration },
+    /// The final result.
+    Result(GameResult),
+}
+
+/// A recorded game, consisting of the raw messages exchanged
+/// with the server and their parsed representation.
+///
+/// Replays are stored as XML, either in our own format (root `<replay>`),
+/// which includes sent moves and timings, or in the official server
+/// format (root `<protocol>`), which only contains states and the result.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    team: Option<Team>,
+    room_id: Option<String>,
+    entries: Vec<ReplayEntry>,
+    /// The raw XML of each entry, in the same order.
+    raw: Vec<Element>,
+}
+
+impl Replay {
+    /// Loads a replay from the given file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::try_from(&fs::read_to_string(path)?.parse::<Element>()?)
+    }
+
+    /// The team of the recording player, if known.
+    pub fn team(&self) -> Option<Team> { self.team }
+
+    /// The room the game was played in, if known.
+    pub fn room_id(&self) -> Option<&str> { self.room_id.as_deref() }
+
+    /// The recorded entries.
+    pub fn entries(&self) -> &[ReplayEntry] { &self.entries }
+
+    /// The recorded game states in order.
+    pub fn states(&self) -> impl Iterator<Item=&State> {
+        self.entries.iter().filter_map(|e| match e {
+            ReplayEntry::State { state, .. } => Some(state.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// The moves sent by the recording player in order.
+    pub fn moves(&self) -> impl Iterator<Item=Move> + '_ {
+        self.entries.iter().filter_map(|e| match e {
+            ReplayEntry::Move { m, .. } => Some(*m),
+            _ => None,
+        })
+    }
+
+    /// The final result, if the game was recorded until the end.
+    pub fn result(&self) -> Option<&GameResult> {
+        self.entries.iter().find_map(|e| match e {
+            ReplayEntry::Result(result) => Some(result),
+            _ => None,
+        })
+    }
+
+    /// Converts the replay to the format of the official server,
+    /// which can be opened in the Software Challenge GUI.
+    pub fn to_server_xml(&self) -> Element {
+        Element::new("protocol")
+            .childs(self.entries.iter().zip(&self.raw).filter_map(|(entry, raw)| match entry {
+                ReplayEntry::State { .. } => raw.child_by_name("state").ok().cloned(),
+                ReplayEntry::Result(_) => raw.child_by_name("data").ok().cloned(),
+                ReplayEntry::Move { .. } => None,
+            }))
+            .build()
+    }
+
+    /// Writes the replay in the official server format to the given file.
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_xml(&self.to_server_xml(), path)
+    }
+}
+
+impl From<&Replay> for Element {
+    fn from(replay: &Replay) -> Self {
+        let mut builder = Element::new("replay").childs(replay.raw.iter().cloned());
+        if let Some(team) = replay.team {
+            builder = builder.attribute("team", team);
+        }
+        if let Some(room_id) = &replay.room_id {
+            builder = builder.attribute("roomId", room_id);
+        }
+        builder.build()
+    }
+}
+
+impl TryFrom<&Element> for Replay {
+    type Error = Error;
+
+    fn try_from(elem: &Element) -> Result<Self> {
+        let mut replay = Replay { team: None, room_id: None, entries: Vec::new(), raw: Vec::new() };
+        match elem.name() {
+            "replay" => {
+                replay.team = elem.attribute("team").ok().map(|t| t.parse()).transpose()?;
+                replay.room_id = elem.attribute("roomId").ok().map(|r| r.to_owned());
+                for child in elem.childs() {
+                    let entry = match child.name() {
+                        "memento" => ReplayEntry::State {
+                            state: Box::new(child.child_by_name("state")?.try_into()?),
+                            elapsed: Duration::from_millis(child.parse_attribute("millis")?),
+                        },
+                        "sent" => ReplayEntry::Move {
+                            turn: child.parse_attribute("turn")?,
+                            m: child.child_by_name("data")?.try_into()?,
+                            duration: Duration::from_millis(child.parse_attribute("millis")?),
+                        },
+                        "result" => ReplayEntry::Result(child.child_by_name("data")?.try_into()?),
+                        _ => return Err(Error::UnknownElement(child.clone())),
+                    };
+                    replay.entries.push(entry);
+                    replay.raw.push(child.clone());
+                }
+            },
+            "protocol" => {
+                for child in elem.childs() {
+                    match child.name() {
+                        "state" => {
+                            replay.entries.push(ReplayEntry::State { state: Box::new(child.try_into()?), elapsed: Duration::ZERO });
+                            replay.raw.push(memento_xml(child, Duration::ZERO));
+                        },
+                        "data" if child.attribute("class").ok() == Some("result") => {
+                            replay.entries.push(ReplayEntry::Result(child.try_into()?));
+                            replay.raw.push(Element::new("result").child(child.clone()).build());
+                        },
+                        _ => return Err(Error::UnknownElement(child.clone())),
+                    }
+                }
+            },
+            _ => return Err(Error::UnknownElement(elem.clone())),
+        }
+        Ok(replay)
+    }
+}
+
+fn memento_xml(state: &Element, elapsed: Duration) -> Element {
+    Element::new("memento")
+        .attribute("millis", elapsed.as_millis())
+        .child(state.clone())
+        .build()
+}
+
+fn write_xml(elem: &Element, path: impl AsRef<Path>) -> Result<()> {
+    let mut writer = Writer::new_with_indent(BufWriter::new(File::create(path)?), b' ', 2);
+    elem.write_to(&mut writer)
+}
+
+/// Records the messages of a game and writes them to a
+/// replay file in the given directory once the game ends.
+#[derive(Debug)]
+pub struct ReplayRecorder {
+    directory: PathBuf,
+    start: Instant,
+    replay: Replay,
+}
+
+impl ReplayRecorder {
+    /// Creates a recorder writing to the given directory.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            start: Instant::now(),
+            replay: Replay { team: None, room_id: None, entries: Vec::new(), raw: Vec::new() },
+        }
+    }
+
+    /// The replay recorded so far.
+    pub fn replay(&self) -> &Replay { &self.replay }
+
+    /// Records the room the game takes place in.
+    pub fn record_room(&mut self, room_id: &str) {
+        self.replay.room_id = Some(room_id.to_owned());
+    }
+
+    /// Records the team of the player.
+    pub fn record_team(&mut self, team: Team) {
+        self.replay.team = Some(team);
+    }
+
+    /// Records a received state along with its raw `<state>` element.
+    pub fn record_state(&mut self, state: State, xml: &Element) {
+        let elapsed = self.start.elapsed();
+        self.replay.raw.push(memento_xml(xml, elapsed));
+        self.replay.entries.push(ReplayEntry::State { state: Box::new(state), elapsed });
+    }
+
+    /// Records a sent move along with the time it took to compute.
+    pub fn record_move(&mut self, turn: usize, m: Move, duration: Duration) {
+        self.replay.raw.push(Element::new("sent")
+            .attribute("turn", turn)
+            .attribute("move", m)
+            .attribute("millis", duration.as_millis())
+            .child(m)
+            .build());
+        self.replay.entries.push(ReplayEntry::Move { turn, m, duration });
+    }
+
+    /// Records the result along with its raw `<data class="result">` element.
+    pub fn record_result(&mut self, result: GameResult, xml: &Element) {
+        self.replay.raw.push(Element::new("result").child(xml.clone()).build());
+        self.replay.entries.push(ReplayEntry::Result(result));
+    }
+
+    /// Writes the replay to a new file in the directory and returns its path.
+    pub fn save(&self) -> Result<PathBuf> {
+        fs::create_dir_all(&self.directory)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let name = format!("replay-{}-{}.xml", timestamp, self.replay.room_id.as_deref().unwrap_or("unknown"));
+        let path = self.directory.join(name);
+        write_xml(&Element::from(&self.replay), &path)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use indoc::indoc;
+
+    use crate::{util::Element, game::{Move, State, Team, Vec2, Doubled}, protocol::GameResult};
+
+    use super::{Replay, ReplayRecorder};
+
+    const STATE: &str = indoc! {r#"
+        <state class="state" turn="1">
+            <startTeam>ONE</startTeam>
+            <board>
+                <list><field>0</field><field>1</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>ONE</field></list>
+            </board>
+            <fishes>
+                <int>1</int>
+                <int>0</int>
+            </fishes>
+        </state>
+    "#};
+
+    const RESULT: &str = indoc! {r#"
+        <data class="result">
+            <definition>
+                <fragment name="Siegpunkte">
+                    <aggregation>SUM</aggregation>
+                    <relevantForRanking>true</relevantForRanking>
+                </fragment>
+            </definition>
+            <scores>
+                <entry>
+                    <player name="rad" team="ONE"/>
+                    <score cause="REGULAR" reason="">
+                        <part>2</part>
+                    </score>
+                </entry>
+            </scores>
+            <winner team="ONE"/>
+        </data>
+    "#};
+
+    fn record() -> Replay {
+        let state_xml: Element = STATE.parse().unwrap();
+        let result_xml: Element = RESULT.parse().unwrap();
+        let mut recorder = ReplayRecorder::new("replays");
+        recorder.record_room("abc");
+        recorder.record_team(Team::Two);
+        recorder.record_state(State::try_from(&state_xml).unwrap(), &state_xml);
+        recorder.record_move(1, Move::placing(Vec2::<Doubled>::new(2, 0)), Duration::from_millis(42));
+        recorder.record_result(GameResult::try_from(&result_xml).unwrap(), &result_xml);
+        recorder.replay().clone()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let replay = record();
+        let loaded = Replay::try_from(&Element::from(&replay).to_string().parse::<Element>().unwrap()).unwrap();
+        assert_eq!(loaded.team(), Some(Team::Two));
+        assert_eq!(loaded.room_id(), Some("abc"));
+        assert_eq!(loaded.states().collect::<Vec<_>>(), replay.states().collect::<Vec<_>>());
+        assert_eq!(loaded.moves().collect::<Vec<_>>(), vec![Move::placing(Vec2::<Doubled>::new(2, 0))]);
+        assert_eq!(loaded.result(), replay.result());
+    }
+
+    #[test]
+    fn test_server_format() {
+        let replay = record();
+        let xml = replay.to_server_xml();
+        assert_eq!(xml.name(), "protocol");
+        assert_eq!(xml.childs().map(|c| c.name()).collect::<Vec<_>>(), vec!["state", "data"]);
+
+        let loaded = Replay::try_from(&xml.to_string().parse::<Element>().unwrap()).unwrap();
+        assert_eq!(loaded.team(), None);
+        assert_eq!(loaded.states().count(), 1);
+        assert_eq!(loaded.moves().count(), 0);
+        assert_eq!(loaded.result(), replay.result());
+    }
+}
@@ -1,9 +1,8 @@
-use std::{collections::{HashMap, HashSet}, cmp::{max, min}};
 use log::info;
 // use neuroflow::{FeedForward, io};
 use time::Instant;
 
-use crate::{game::{State, Team, Vec2, Doubled, self}, spots::get_spot_scores};
+use crate::{game::{State, Team, Vec2, Doubled, self}, spots::get_spot_scores, patterns::{self, get_pattern_scores}, analysis::{Distances, Reachability, FieldSet, confinements, threatened_penguins, cut_opportunities}};
 
 pub fn get_move_num(gamestate:&State, my_turn:i32) -> f32 {
     my_turn as f32  * (gamestate.possible_moves().len() as f32 - gamestate.opponent_moves().len() as f32)
@@ -45,37 +44,15 @@ pub fn get_pengu(gamestate:&State, my_turn:i32) -> f32 {
 // }
 
 pub fn dj_activator(d:i32, f:usize) -> f32 {
-    4. * f as f32 * (1. - 1. / (1. + 2.71828_f32.powf(-d as f32)) )
-}
-
-
-pub fn get_field_control(gamestate:&State, field:Vec2<Doubled>, h:&mut HashMap<Vec2<Doubled>, i32>, val:i32) {
-    for new_field in gamestate.board().possible_moves_from(field) {
-        if let Some(k) = h.get_mut(&new_field.to()) {
-            *k+=1;
-        } else {
-            h.insert(new_field.to(), val);
-            get_field_control(gamestate, new_field.to(), h, val);
-        }
-    }
+    4. * f as f32 * (1. - 1. / (1. + std::f32::consts::E.powf(-d as f32)) )
 }
 
 
 pub fn get_controlled_fields_of(gamestate:&State, team1:Team, team2:Team) -> i32 {
-    let pingus1 = gamestate.pieces_of(team1);
-    let pingus2 = gamestate.pieces_of(team2);
-    let mut hm: HashMap<Vec2<Doubled>, i32> = HashMap::new();
-    let mut count = 0;
-    for position in pingus1 {
-        get_field_control(gamestate, position.0, &mut hm, 1) ;
-    }
-    for position in pingus2 {
-        get_field_control(gamestate, position.0, &mut hm, -1) ;
-    }
-    for key in hm.keys() {
-        count += max(-1, min(1, *hm.get(key).unwrap())) * gamestate.board()[*key].fish() as i32;
-    }  
-    return count;
+    let board = gamestate.board();
+    let reachable1 = Distances::of_team(board, team1).reachable();
+    let reachable2 = Distances::of_team(board, team2).reachable();
+    (reachable1 - reachable2).fish(board) as i32 - (reachable2 - reachable1).fish(board) as i32
 }
 
 pub fn get_controlled_fields(gamestate:&State, my_turn:i32) -> f32 {
@@ -83,52 +60,24 @@ pub fn get_controlled_fields(gamestate:&State, my_turn:i32) -> f32 {
 }
 
 
-fn get_field_levels_from(gamestate:&State, t_fields:&Vec<Vec2<Doubled>>) -> (f32) {
-    let mut this_fields = t_fields.clone();
-    let mut done_fields:Vec<Vec2<Doubled>> = vec![];
-    let mut next_fields:Vec<Vec2<Doubled>>;
-    let mut i = 1;
-    let mut count:f32 = 0.;
-    while this_fields.len() > 0 {
-        next_fields = vec![];
-        for field in &this_fields {
-            count+=dj_activator(i, gamestate.board()[*field].fish()) as f32;
-            done_fields.push(*field);
-        }
-        for field in this_fields {
-            for new_field in gamestate.board().possible_moves_from(field) {
-                if !(next_fields.contains(&new_field.to()) || done_fields.contains(&new_field.to())) {
-                    next_fields.push(new_field.to());
-                }
-            }
-        }
-        this_fields = next_fields;
-        i+=1;
-    }
-    return count;
+fn get_field_levels_from(gamestate:&State, distances:&Distances, offset:i32) -> f32 {
+    distances.iter()
+        .map(|(c, d)| dj_activator(d as i32 + offset, gamestate.board()[c].fish()))
+        .sum()
 }
 
 pub fn get_pengu_mobility_of(gamestate:&State, team1:Team) -> f32 {
     let pingus1 = gamestate.pieces_of(team1);
     let mut c1 = 0.;
     for position in pingus1 {
-        c1 += get_field_levels_from(gamestate, &vec![position.0]);
+        c1 += get_field_levels_from(gamestate, &Distances::of_penguin(gamestate.board(), position.0), 1);
     }
     return c1;
 }
 
 
-pub fn get_field_levels_of(gamestate:&State, team1:Team) -> (f32) {
-    let pingus1 = gamestate.pieces_of(team1);
-    let mut this_fields:Vec<Vec2<Doubled>> = vec![];
-    for position in pingus1 {
-        for m in gamestate.board().possible_moves_from(position.0) {
-            if (!this_fields.contains(&m.to())) {
-                this_fields.push(m.to());
-            }
-        }
-    }
-    return get_field_levels_from(gamestate, &this_fields, );
+pub fn get_field_levels_of(gamestate:&State, team1:Team) -> f32 {
+    return get_field_levels_from(gamestate, &Distances::of_team(gamestate.board(), team1), 0);
 }
 
 
@@ -147,6 +96,46 @@ pub fn get_pengu_mobility(gamestate:&State, my_turn:i32) -> (f32) {
 }
 
 
+pub fn get_isolated_fish_of(gamestate:&State, team:Team) -> f32 {
+    let mut region = FieldSet::EMPTY;
+    for c in confinements(gamestate.board(), team) {
+        if c.is_isolated() {
+            region = region | c.region;
+        }
+    }
+    region.fish(gamestate.board()) as f32
+}
+
+pub fn get_isolated_fish(gamestate:&State, my_turn:i32) -> f32 {
+    my_turn as f32 * (get_isolated_fish_of(gamestate, gamestate.current_team())
+             - get_isolated_fish_of(gamestate, gamestate.current_team().opponent()))
+}
+
+pub fn get_trapped_penguins_of(gamestate:&State, team:Team) -> f32 {
+    confinements(gamestate.board(), team).iter().filter(|c| c.is_trapped() && c.shared).count() as f32
+}
+
+pub fn get_trapped_penguins(gamestate:&State, my_turn:i32) -> f32 {
+    my_turn as f32 * (get_trapped_penguins_of(gamestate, gamestate.current_team())
+             - get_trapped_penguins_of(gamestate, gamestate.current_team().opponent()))
+}
+
+pub fn get_threatened_penguins(gamestate:&State, my_turn:i32) -> f32 {
+    my_turn as f32 * (threatened_penguins(gamestate, gamestate.current_team()) as f32
+             - threatened_penguins(gamestate, gamestate.current_team().opponent()) as f32)
+}
+
+
+pub fn get_cut_gain_of(gamestate:&State, team:Team) -> f32 {
+    cut_opportunities(gamestate, team).iter().map(|c| c.gain).max().unwrap_or(0).max(0) as f32
+}
+
+pub fn get_cut_gain(gamestate:&State, my_turn:i32) -> f32 {
+    my_turn as f32 * (get_cut_gain_of(gamestate, gamestate.current_team())
+             - get_cut_gain_of(gamestate, gamestate.current_team().opponent()))
+}
+
+
 fn get_distance(v1 : Vec2<Doubled>, v2:Vec2<Doubled>) -> f32 {
     return (v1-v2).length();
 }
@@ -247,47 +236,32 @@ const args1: &[f32] = &[0.34631, -0.58898, 0.39159, -0.70054, 0.06327, -0.52274,
 //         ;
 // }
 
-pub fn evaluate(s:&State, my_turn:i32) -> f32 
-{
+/// Scores the fish each team holds or reaches first.
+fn score(s:&State, my_turn:i32, reachability:&Reachability) -> f32 {
     let mut my_team = s.current_team();
     if my_turn == -1 {
         my_team = s.current_team().opponent();
     }
-    let fishes = |team: Team| -> [u8; 64] { 
-        let mut steps: [u8; 64] = [u8::MAX; 64];
-        let mut queue: Vec<Vec2<Doubled>> = s.board().penguins().filter(|p| p.1 == team).map(|p| p.0).collect();
-        let mut visited: HashSet<Vec2<Doubled>> = HashSet::new();
-        let mut step = 2;
-        while !queue.is_empty() {
-            let mut temp: Vec<Vec2<Doubled>> = Vec::new();
-            for f in queue {
-                for m in s.board().possible_moves_from(f) {
-                    if !visited.contains(&m.to()) {
-                        visited.insert(m.to());
-                        steps[(m.to().y*8+m.to().x/2) as usize] = step;
-                        temp.push(m.to());
-                    }
-                }
-            }
-            queue = temp;
-            step += 1;
-        }
-        steps
-    };
-    let steps_us = fishes(my_team);
-    let steps_opponent = fishes(my_team.opponent());
-    let mut fish_us = s.fish(my_team) as f64;
-    let mut fish_opponent = s.fish(my_team.opponent()) as f64;
-    for (c,f) in s.board().fields() {
-        if f.is_empty() {continue;}
-        let i = (c.y*8+c.x/2) as usize;
-        let fish = f.fish() as f64;
-        if steps_us[i] > steps_opponent[i] {fish_opponent += fish;} else if steps_us[i] < steps_opponent[i] {fish_us += fish;}
-    }
+    let voronoi = reachability.voronoi(s.board());
+    let fish_us = (s.fish(my_team) + voronoi.fish_of(my_team)) as f64;
+    let fish_opponent = (s.fish(my_team.opponent()) + voronoi.fish_of(my_team.opponent())) as f64;
     let result = fish_us - fish_opponent;
     return (result * 2. - 1.) as f32;
 }
 
+pub fn evaluate(s:&State, my_turn:i32) -> f32 
+{
+    score(s, my_turn, &Reachability::of(s.board()))
+}
+
+/// Evaluates the position unless it is tactically sharp, i.e. a penguin
+/// fights over a small region with an opponent (see `extension_trigger`),
+/// which is left to a deeper search. Shares the distances between both.
+pub fn evaluate_quiet(s:&State, my_turn:i32) -> Option<f32> {
+    let reachability = Reachability::of(s.board());
+    (reachability.trapped_penguins(s.board()) == [0, 0]).then(|| score(s, my_turn, &reachability))
+}
+
 // const args1: &[f32] = &[ 0.27510019,  0.26416571,  0.06679495, 0.0832137, 0., 0., 0., 0.];
 // // const args1: &[f32] = &[0.23317, -1.07372, 0.27044, -0.61939, 0.06002, 0.74372, 0.03093, -0.9385, -0.01514, 0.20597]
 // pub fn evaluate(gamestate:&mut State, my_turn:i32) -> f32 {
@@ -340,7 +314,7 @@ pub fn print_eval(gamestate:&mut State, my_turn:i32) {
     info!("Arg 2: {}", args1[2] * lateness.powf(args1[3]) * f);
     info!("Arg 3: {}", args1[4] * lateness.powf(args1[5]) * get_pengu(gamestate, my_turn) as f32);
     // info!("Arg 4: {}", args1[6] * lateness.powf(args1[7]) * get_move_num(gamestate, my_turn));
-    info!("Arg 5: {}", args1[6] * lateness.powf(args1[7]) * get_spot_scores(gamestate, my_turn));
+    info!("Arg 5: {}", args1[6] * lateness.powf(args1[7]) * get_neighborhood_scores(gamestate, my_turn));
     // info!("Arg 5: {}", args1[8] * lateness.powf(args1[9]) * get_pingu_dist_diff(gamestate, my_turn));
     // info!("Arg 6: {}", args1[10] * lateness.powf(args1[11]) * get_mobbing_val(gamestate, my_turn));
     //info!("Arg 5: {}", args[8] * lateness.powf(args[9]) * c);      
@@ -355,7 +329,13 @@ pub fn test_speed(gamestate:&State) {
     test_speed_single(get_pingu_dist_diff, gamestate);
     test_speed_single(get_pingu_enemy_dist_diff, gamestate);
     test_speed_single(get_controlled_fields, gamestate);
-    test_speed_single(get_spot_scores, gamestate)
+    test_speed_single(get_spot_scores, gamestate);
+    test_speed_single(get_pattern_scores, gamestate);
+    test_speed_single(get_neighborhood_scores, gamestate);
+    test_speed_single(get_isolated_fish, gamestate);
+    test_speed_single(get_trapped_penguins, gamestate);
+    test_speed_single(get_threatened_penguins, gamestate);
+    test_speed_single(get_cut_gain, gamestate)
    // test_speed_single(get_game_sim, gamestate);
 }
 
@@ -392,10 +372,23 @@ fn get_model_args(gamestate:&State, my_turn:i32) -> Vec<f32> {
         get_fish_dif(gamestate, my_turn),
         f,
         get_pengu(gamestate, my_turn),
-        get_spot_scores(gamestate, my_turn),
+        get_neighborhood_scores(gamestate, my_turn),
+        get_isolated_fish(gamestate, my_turn),
+        get_trapped_penguins(gamestate, my_turn),
+        get_threatened_penguins(gamestate, my_turn),
+        get_cut_gain(gamestate, my_turn),
     ]
 }
 
+/// Scores the penguins' neighborhoods with the installed pattern table,
+/// falling back to the hand-made spot scores while none is installed.
+pub fn get_neighborhood_scores(gamestate:&State, my_turn:i32) -> f32 {
+    match patterns::installed_table() {
+        Some(table) => table.evaluate(gamestate, my_turn),
+        None => get_spot_scores(gamestate, my_turn),
+    }
+}
+
 pub fn toStringVec(inp:Vec<f32>) -> Vec<String> {
     let mut out = vec![];
     for v in inp {
@@ -409,3 +402,75 @@ pub fn get_vals_as_str(gamestate:&State, my_turn:i32) -> String {
     return toStringVec(get_model_args(gamestate, my_turn)).join(";");
 }
 
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+    use indoc::indoc;
+
+    use crate::{analysis::Reachability, game::{Board, State, Team}, patterns, spots::get_spot_scores};
+
+    use super::{evaluate, evaluate_quiet, get_cut_gain, get_model_args};
+
+    #[test]
+    fn test_spot_scores_without_table() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut state = State::new(Board::random(&mut rng), 0, [0, 0], None, Team::One);
+        // Play on until the spot scores tell the teams apart
+        while get_spot_scores(&state, 1) == 0.0 {
+            let m = *state.possible_moves().choose(&mut rng).unwrap();
+            state.perform(m);
+        }
+        // No test installs a table, which would change the feature for good
+        assert!(patterns::installed_table().is_none());
+        assert_eq!(get_model_args(&state, 1)[4], get_spot_scores(&state, 1));
+        assert_eq!(get_model_args(&state, -1)[4], get_spot_scores(&state, -1));
+    }
+
+    #[test]
+    fn test_trapped_penguin_is_not_quiet() {
+        // The red penguin at the top left can only reach the two floes next
+        // to it, which the blue one can reach too, while blue can escape
+        // downwards. The other penguins are stuck.
+        let mut board: Board = indoc! {r#"
+            R21B0000
+            00011111
+            11111111
+            11111111
+            00000000
+            B0B0B000
+            00000000
+            R0R0R000
+        "#}.parse().unwrap();
+        let state = State::new(board, 8, [0, 0], None, Team::One);
+        assert_eq!(Reachability::of(&board).trapped_penguins(&board), [1, 0]);
+        assert_eq!(get_model_args(&state, 1)[6], 1.0);
+        assert_eq!(evaluate_quiet(&state, 1), None);
+
+        // Without the floes between them, red is isolated rather than trapped
+        board = board.to_string().replacen("R21B", "R200", 1).parse().unwrap();
+        let state = State::new(board, 8, [0, 0], None, Team::One);
+        assert_eq!(evaluate_quiet(&state, 1), Some(evaluate(&state, 1)));
+    }
+
+    #[test]
+    fn test_cut_gain_feature() {
+        // Red can move onto the bridge between two triangles of floes,
+        // leaving the left one to itself and gaining its 6 fish
+        let board: Board = indoc! {r#"
+            22122000
+            2R02B000
+            00000000
+            00000000
+            00000000
+            B0B0B000
+            00000000
+            R0R0R000
+        "#}.parse().unwrap();
+        let state = State::new(board, 8, [0, 0], None, Team::One);
+        assert_eq!(get_cut_gain(&state, 1), 6.0);
+        assert_eq!(get_cut_gain(&state, -1), -6.0);
+        assert_eq!(get_model_args(&state, 1)[8], 6.0);
+    }
+}
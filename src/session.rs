@@ -0,0 +1,446 @@
+use std::mem;
+use std::path::PathBuf;
+use std::time::Duration;
+use log::{info, warn, debug, error};
+use quick_xml::events::{Event as XmlEvent, BytesStart};
+use quick_xml::Writer;
+use crate::game::{State, Team, Move};
+use crate::protocol::{Request, Event, GameResult, EventPayload, RequestPayload};
+use crate::replay::ReplayRecorder;
+use crate::util::{Result, Element, Error, XmlDecoder};
+
+/// Something the player has to react to, produced by a `Session`.
+#[derive(Debug)]
+pub enum SessionEvent {
+    /// A game was joined.
+    GameStarted { room_id: String },
+    /// The team of the player in the current game.
+    Welcome(Team),
+    /// The game state was updated.
+    StateUpdated(State),
+    /// The server requests a move in the given state, which
+    /// is to be answered with `Session::send_move`.
+    MoveRequested(State),
+    /// The current game ended.
+    GameEnded(GameResult),
+    /// The room was left, with the replay of the game if one was recorded.
+    Left { room_id: String, replay: Option<ReplayRecorder> },
+}
+
+/// Picks a quick move for emergencies, greedily
+/// taking the floe with the most fish.
+pub fn fallback_move(state: &State) -> Option<Move> {
+    state.possible_moves()
+        .into_iter()
+        .max_by_key(|m| state.board()[m.to()].fish())
+}
+
+/// Passes the given move through if it is legal, otherwise
+/// logs the problem and picks a fallback move.
+fn checked_move(state: &State, m: Move) -> Option<Move> {
+    match state.check_move(m) {
+        Ok(()) => Some(m),
+        Err(e) => {
+            // Sending an illegal move would lose the game instantly
+            let fallback = fallback_move(state);
+            error!("Delegate chose illegal move {} ({}), falling back to {:?}", m, e, fallback);
+            fallback
+        },
+    }
+}
+
+/// The protocol of a player connection as a state machine without any
+/// I/O: bytes received from the server are passed in, events for the
+/// player are taken out, and the bytes to send are collected until the
+/// driving client writes them. This lets blocking and asynchronous
+/// clients share the protocol handling.
+pub struct Session {
+    decoder: XmlDecoder,
+    writer: Writer<Vec<u8>>,
+    join: Request,
+    games: Option<usize>,
+    replay_dir: Option<PathBuf>,
+    handshake: bool,
+    /// The room of the game currently played.
+    room_id: Option<String>,
+    /// Whether the connection was reestablished and the
+    /// state is not yet confirmed by a new memento.
+    resuming: bool,
+    state: Option<State>,
+    /// The room in which a move was requested and not yet sent.
+    move_room: Option<String>,
+    game_result: Option<GameResult>,
+    game_results: Vec<GameResult>,
+    recorder: Option<ReplayRecorder>,
+    finished: bool,
+}
+
+impl Session {
+    /// Creates a session that opens the connection with the given join request.
+    pub fn new(join: Request) -> Self {
+        let mut session = Self {
+            decoder: XmlDecoder::new(),
+            writer: Writer::new(Vec::new()),
+            join,
+            games: Some(1),
+            replay_dir: None,
+            handshake: false,
+            room_id: None,
+            resuming: false,
+            state: None,
+            move_room: None,
+            game_result: None,
+            game_results: Vec::new(),
+            recorder: None,
+            finished: false,
+        };
+        session.open();
+        session
+    }
+
+    /// Starts a new connection with the join request.
+    fn open(&mut self) {
+        self.decoder = XmlDecoder::new();
+        self.decoder.expect_root("protocol");
+        self.handshake = false;
+        self.writer.inner().clear();
+
+        // Writing into memory cannot fail
+        let _ = self.writer.write_event(XmlEvent::Start(BytesStart::borrowed_name(b"protocol")));
+        let join_xml = Element::from(self.join.clone());
+        info!("Sending join request {}", &join_xml);
+        self.send(join_xml);
+    }
+
+    /// Plays the given number of games, or keeps playing until
+    /// the server closes the connection if `None`. After the
+    /// first game, arbitrary open games are joined.
+    pub fn with_games(mut self, games: Option<usize>) -> Self {
+        self.games = games;
+        self
+    }
+
+    /// Records a replay of each game into the given directory.
+    pub fn with_replay_dir(mut self, replay_dir: impl Into<PathBuf>) -> Self {
+        self.replay_dir = Some(replay_dir.into());
+        self
+    }
+
+    /// Passes bytes received from the server.
+    pub fn receive(&mut self, bytes: &[u8]) {
+        self.decoder.feed(bytes);
+    }
+
+    /// Signals that the server closed the connection.
+    pub fn close(&mut self) {
+        self.decoder.finish();
+    }
+
+    /// Takes the bytes to send to the server.
+    pub fn take_outgoing(&mut self) -> Vec<u8> {
+        mem::take(self.writer.inner())
+    }
+
+    /// Whether all games are played or the connection was closed.
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    /// Whether the game was joined with a reservation and has not
+    /// ended yet, so that it can be resumed over a new connection.
+    pub fn can_resume(&self) -> bool {
+        matches!(self.join, Request::JoinPrepared { .. }) && self.game_result.is_none() && self.game_results.is_empty()
+    }
+
+    /// Continues the session over a new connection after the previous
+    /// one dropped, joining the reserved game again. Unsent bytes and
+    /// a pending move request are discarded, the state is kept until
+    /// the server sends a new memento.
+    pub fn resume(&mut self) -> Result<()> {
+        if !self.can_resume() {
+            return Err(Error::NotResumable);
+        }
+        self.finished = false;
+        self.move_room = None;
+        self.resuming = true;
+        self.open();
+        Ok(())
+    }
+
+    /// The results of the games played so far.
+    pub fn results(&self) -> &[GameResult] { &self.game_results }
+
+    /// The results of all games played, failing if there are none.
+    pub fn into_results(self) -> Result<Vec<GameResult>> {
+        if self.game_results.is_empty() {
+            Err(Error::NoGameResult)
+        } else {
+            Ok(self.game_results)
+        }
+    }
+
+    /// Processes the received bytes up to the next event, `None` if more
+    /// input is needed or the session is finished. Malformed messages are
+    /// logged and skipped.
+    pub fn poll_event(&mut self) -> Result<Option<SessionEvent>> {
+        while !self.finished {
+            let next = self.decoder.next_element();
+            if !self.handshake && self.decoder.in_root() {
+                info!("Performed handshake");
+                self.handshake = true;
+            }
+            let event_xml = match next {
+                None => return Ok(None),
+                Some(Ok(event_xml)) => event_xml,
+                Some(Err(Error::Eof)) => {
+                    self.finished = true;
+                    if self.game_results.is_empty() || self.room_id.is_some() {
+                        return Err(Error::Eof);
+                    }
+                    info!("Server closed the connection");
+                    return Ok(None);
+                },
+                Some(Err(Error::Malformed { position, message })) => {
+                    error!("Skipping malformed message at {}: {}", position, message);
+                    continue;
+                },
+                Some(Err(Error::LimitExceeded { position, message })) => {
+                    error!("Skipping message at {}: {}", position, message);
+                    continue;
+                },
+                Some(Err(e)) => return Err(e),
+            };
+            if let Some(event) = self.handle(&event_xml)? {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Answers the pending move request with the given move, which is
+    /// checked for legality, or with a fallback move if `None`. Returns
+    /// the move that was sent, `None` if there was no legal one.
+    pub fn send_move(&mut self, m: Option<Move>, duration: Duration) -> Result<Option<Move>> {
+        let room_id = self.move_room.take().ok_or(Error::NoMoveRequested)?;
+        let state = self.state.ok_or(Error::NoState)?;
+        let new_move = match m {
+            Some(m) => checked_move(&state, m),
+            None => fallback_move(&state),
+        };
+        match new_move {
+            Some(new_move) => {
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.record_move(state.turn(), new_move, duration);
+                }
+                self.send(Request::Room { room_id, payload: RequestPayload::Move(new_move) }.into());
+            },
+            None => error!("No legal move available to send"),
+        }
+        Ok(new_move)
+    }
+
+    fn send(&mut self, xml: Element) {
+        let _ = xml.write_to(&mut self.writer);
+    }
+
+    fn handle(&mut self, event_xml: &Element) -> Result<Option<SessionEvent>> {
+        debug!("Got event {}", event_xml);
+        let event = match Event::try_from(event_xml) {
+            Ok(Event::Joined { room_id }) if self.resuming && self.room_id.as_ref() == Some(&room_id) => {
+                info!("Rejoined room {}", room_id);
+                return Ok(None);
+            },
+            Ok(Event::Joined { room_id }) => {
+                info!("Joined room {}", room_id);
+                self.room_id = Some(room_id.clone());
+                self.resuming = false;
+                self.recorder = self.replay_dir.as_ref().map(ReplayRecorder::new);
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.record_room(&room_id);
+                }
+                self.state = None;
+                SessionEvent::GameStarted { room_id }
+            },
+            Ok(Event::Left { room_id }) => {
+                info!("Left room {}", room_id);
+                self.room_id = None;
+                self.resuming = false;
+                match self.game_result.take() {
+                    Some(result) => self.game_results.push(result),
+                    None => warn!("Left room {} without a game result", room_id),
+                }
+                if self.games.is_some_and(|games| self.game_results.len() >= games) {
+                    self.finished = true;
+                } else {
+                    let join_xml = Element::from(Request::Join);
+                    info!("Played {} game(s), sending join request {}", self.game_results.len(), &join_xml);
+                    self.send(join_xml);
+                }
+                SessionEvent::Left { room_id, replay: self.recorder.take() }
+            },
+            Ok(Event::Room { room_id, payload }) => {
+                info!("Got {} in room {}", payload, room_id);
+                let data_xml = event_xml.child_by_name("data")?;
+                match payload {
+                    EventPayload::Welcome(team) => {
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record_team(team);
+                        }
+                        SessionEvent::Welcome(team)
+                    },
+                    EventPayload::GameResult(result) => {
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record_result(result.clone(), data_xml);
+                        }
+                        self.game_result = Some(result.clone());
+                        SessionEvent::GameEnded(result)
+                    },
+                    EventPayload::Memento(new_state) => {
+                        if mem::take(&mut self.resuming) {
+                            info!("Resynchronized at turn {}", new_state.turn());
+                            if self.state == Some(new_state) {
+                                // Already recorded and passed to the delegate
+                                return Ok(None);
+                            }
+                        }
+                        debug!("State {}", new_state);
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.record_state(new_state, data_xml.child_by_name("state")?);
+                        }
+                        self.state = Some(new_state);
+                        SessionEvent::StateUpdated(new_state)
+                    },
+                    EventPayload::MoveRequest => {
+                        if self.resuming {
+                            warn!("Move requested before resynchronizing, using the last known state");
+                        }
+                        let state = self.state.ok_or(Error::NoState)?;
+                        self.move_room = Some(room_id);
+                        SessionEvent::MoveRequested(state)
+                    },
+                }
+            },
+            Ok(event) => {
+                warn!("Got unexpected administrator event {:?}", event);
+                return Ok(None);
+            },
+            Err(Error::UnknownElement(element)) => {
+                warn!("Got unknown tag <{}>: {}", element.name(), element);
+                return Ok(None);
+            },
+            Err(e @ Error::ServerError { .. }) => {
+                error!("{}", e);
+                return Ok(None);
+            },
+            Err(e) => {
+                warn!("Error while parsing event: {}", e);
+                return Ok(None);
+            },
+        };
+        Ok(Some(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{game::{Move, Vec2, Doubled, State}, protocol::{Event, EventPayload, Request}, util::{Element, Error}};
+
+    use super::{Session, SessionEvent};
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::new(Request::Join);
+        assert_eq!(String::from_utf8(session.take_outgoing()).unwrap(), r#"<protocol><join gameType="swc_2023_penguins"/>"#);
+
+        // Input may arrive in arbitrary pieces
+        let input = r#"
+            <protocol>
+                <joined roomId="a"/>
+                <room roomId="a">
+                    <data class="memento">
+                        <state class="state" turn="0">
+                            <startTeam>ONE</startTeam>
+                            <board>
+                                <list><field>1</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field></list>
+                                <list><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>0</field><field>1</field></list>
+                            </board>
+                            <fishes><int>0</int><int>0</int></fishes>
+                        </state>
+                    </data>
+                </room>
+                <room roomId="a"><data class="moveRequest"/></room>
+        "#;
+        let mut events = Vec::new();
+        for chunk in input.as_bytes().chunks(7) {
+            session.receive(chunk);
+            while let Some(event) = session.poll_event().unwrap() {
+                events.push(event);
+            }
+        }
+        assert!(matches!(&events[..], [
+            SessionEvent::GameStarted { .. },
+            SessionEvent::StateUpdated(_),
+            SessionEvent::MoveRequested(state),
+        ] if state.turn() == 0));
+
+        // An illegal move is replaced by a legal one, and only one move is sent per request
+        session.send_move(Some(Move::placing(Vec2::<Doubled>::new(2, 0))), Duration::ZERO).unwrap();
+        assert!(matches!(session.send_move(None, Duration::ZERO), Err(Error::NoMoveRequested)));
+        let output = String::from_utf8(session.take_outgoing()).unwrap();
+        assert!(output.starts_with(r#"<room roomId="a"><data class="move"><to "#));
+        assert!(!output.contains(r#"x="2""#));
+
+        session.close();
+        assert!(session.poll_event().is_err());
+        assert!(session.is_finished());
+    }
+
+    #[test]
+    fn test_resume() {
+        let state: State = "10000000/00000000/00000000/00000000/00000000/00000000/00000000/00000001 R 0 0:0 -".parse().unwrap();
+        let room = |payload| Element::from(Event::Room { room_id: "a".to_owned(), payload }).to_string();
+        let joined = Element::from(Event::Joined { room_id: "a".to_owned() }).to_string();
+        let poll_all = |session: &mut Session| {
+            let mut events = Vec::new();
+            while let Some(event) = session.poll_event().unwrap() {
+                events.push(event);
+            }
+            events
+        };
+
+        // Only reserved games can be resumed
+        let mut session = Session::new(Request::Join);
+        session.receive(format!("<protocol>{}{}", joined, room(EventPayload::Memento(state))).as_bytes());
+        poll_all(&mut session);
+        session.close();
+        assert!(matches!(session.poll_event(), Err(Error::Eof)));
+        assert!(!session.can_resume());
+        assert!(matches!(session.resume(), Err(Error::NotResumable)));
+
+        let mut session = Session::new(Request::JoinPrepared { reservation_code: "abc".to_owned() });
+        session.take_outgoing();
+        session.receive(format!("<protocol>{}{}{}", joined, room(EventPayload::Memento(state)), room(EventPayload::MoveRequest)).as_bytes());
+        assert_eq!(poll_all(&mut session).len(), 3);
+        session.close();
+        assert!(matches!(session.poll_event(), Err(Error::Eof)));
+        assert!(session.can_resume());
+
+        // The reservation is sent again, and the pending move request is dropped
+        session.resume().unwrap();
+        assert!(!session.is_finished());
+        assert_eq!(String::from_utf8(session.take_outgoing()).unwrap(), r#"<protocol><joinPrepared reservationCode="abc"/>"#);
+        assert!(matches!(session.send_move(None, Duration::ZERO), Err(Error::NoMoveRequested)));
+
+        // Rejoining and the known state are not reported again
+        session.receive(format!("<protocol>{}{}{}", joined, room(EventPayload::Memento(state)), room(EventPayload::MoveRequest)).as_bytes());
+        assert!(matches!(&poll_all(&mut session)[..], [SessionEvent::MoveRequested(s)] if *s == state));
+        session.send_move(None, Duration::ZERO).unwrap();
+        assert!(!session.take_outgoing().is_empty());
+    }
+}
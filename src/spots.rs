@@ -1,71 +1,88 @@
-use crate::game::{State, Team, Vec2, Doubled};
+use crate::game::{Board, State, Team, Vec2, Doubled};
 
+// The spot classifier looks at the 6 fields around a penguin and
+// sorts the neighborhood into one of four classes:
+//
+// - 'y' (yellow): exactly one neighboring floe, which is isolated.
+//   The penguin sits at a dead end with a single way out.
+// - 'b' (black): the neighboring floes are all isolated from each
+//   other (no two of them touch) and at least one of them faces an
+//   empty field across the penguin.
+// - 'r' (red): at least one isolated neighboring floe next to other
+//   floes, or exactly two groups of floes separated by an empty axis.
+//   Leaving the spot tends to split the surrounding floes.
+// - 'w' (white): everything else, i.e. connected neighborhoods as well
+//   as fully open or fully enclosed spots.
 
-fn own_is_valid(cord:Vec2<Doubled>) -> bool{
-    return 0 <= cord.x && cord.x < 8 && 0 <= cord.y && cord.y < 8
-}
+/// The hex directions in circular order, such that neighboring
+/// entries are adjacent and entries 3 apart are opposite.
+const CIRCULAR_DIRECTIONS: [Vec2<Doubled>; 6] = [
+    Vec2::<Doubled>::LEFT,
+    Vec2::<Doubled>::UP_RIGHT,
+    Vec2::<Doubled>::UP_LEFT,
+    Vec2::<Doubled>::RIGHT,
+    Vec2::<Doubled>::DOWN_LEFT,
+    Vec2::<Doubled>::DOWN_RIGHT,
+];
+
+/// Classifies the neighborhood of the given field on the board,
+/// see the module comment for the meaning of the classes.
+pub fn spot_name(board: &Board, root: Vec2<Doubled>) -> char {
+    let neighbors = CIRCULAR_DIRECTIONS.map(|d| board.get(root + d).is_some_and(|f| f.fish() > 0));
+    let next = |i: usize| neighbors[(i + 1) % 6];
+    let previous = |i: usize| neighbors[(i + 5) % 6];
+    let opposite = |i: usize| neighbors[(i + 3) % 6];
 
-fn get_spot_name(gamestate:&State, root:Vec2<Doubled>) -> char {
     let mut count = 0;
     let mut redspot = false;
     let mut blackspot = true;
     let mut empty_mirror = false;
-
     let mut mirror = true;
 
-    let mut neighbors:Vec<bool> = vec![];
-
-    for n in root.hex_neighbors() {
-        if own_is_valid(n) {
-            neighbors.push(gamestate.board()[n].fish() > 0)
-        } else {
-            neighbors.push(false)
-        }
-    }
-
-    for i in 0..6 {
-        if neighbors[i] {
-            if neighbors[(i+1) % 6] {
+    for (i, &floe) in neighbors.iter().enumerate() {
+        if floe {
+            if next(i) {
                 count += 1;
                 blackspot = false;
-            } else if !neighbors[(i-1)%6] {
+            } else if !previous(i) {
                 count += 1;
                 redspot = true;
             }
-        } else if !neighbors[(i-3)%6] {
+        } else if !opposite(i) {
             empty_mirror = true;
+        } else {
+            mirror = false;
         }
-        else if neighbors[(i-3)%6] {
-            mirror = false
-        }    
     }
     if empty_mirror && count == 2 {
         redspot = true;
     }
-    let mut spot = 'w';
+
     if count == 1 && redspot {
-        spot = 'y'
+        'y'
     } else if blackspot && !mirror {
-        spot = 'b'
-    }
-    else if redspot {
-        spot = 'r'
+        'b'
+    } else if redspot {
+        'r'
+    } else {
+        'w'
     }
-    return spot;
 }
 
-const scores:&[f32] = &[0.85, -0.6, 0.3, -1.1];
-// const scores:&[f32] = &[1.3, 0.9, -0.7, -2.];
+/// Classifies the neighborhood of the given field in the game state.
+pub fn get_spot_name(gamestate:&State, root:Vec2<Doubled>) -> char {
+    spot_name(gamestate.board(), root)
+}
+
+const SCORES:&[f32] = &[0.85, -0.6, 0.3, -1.1];
+// const SCORES:&[f32] = &[1.3, 0.9, -0.7, -2.];
 pub fn get_spot_score(gamestate:&State, spot:Vec2<Doubled>) -> f32 {
-    let name = get_spot_name(gamestate, spot);
-    if name== 'y'  {
-        return scores[0]
-    } else if name == 'w' {
-        return scores[1]
-    } else if name == 'r' {
-        return scores[2]
-    } 
-    return scores[3]; 
+    match get_spot_name(gamestate, spot) {
+        'y' => SCORES[0],
+        'w' => SCORES[1],
+        'r' => SCORES[2],
+        _ => SCORES[3],
+    }
 }
 
 pub fn get_pingu_spot_scores(gamestate:&State, team:Team) -> f32 {
@@ -78,8 +95,145 @@ pub fn get_pingu_spot_scores(gamestate:&State, team:Team) -> f32 {
 }
 
 pub fn get_spot_scores(gamestate:&State, my_turn:i32) -> f32 {
-    my_turn as f32 * ( 
+    my_turn as f32 * (
         get_pingu_spot_scores(&gamestate, gamestate.current_team()) -
         get_pingu_spot_scores(&gamestate, gamestate.current_team().opponent())
     )
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::game::{Board, Vec2, Direct};
+
+    use super::spot_name;
+
+    fn spot_at_center(board: &str) -> char {
+        spot_name(&board.parse::<Board>().unwrap(), Vec2::<Direct>::new(3, 3).into())
+    }
+
+    #[test]
+    fn test_yellow_spot() {
+        assert_eq!(spot_at_center(indoc! {r#"
+            00000000
+            00000000
+            00000000
+            000R1000
+            00000000
+            00000000
+            00000000
+            00000000
+        "#}), 'y');
+    }
+
+    #[test]
+    fn test_black_spot() {
+        assert_eq!(spot_at_center(indoc! {r#"
+            00000000
+            00000000
+            00010000
+            000R1000
+            00000000
+            00000000
+            00000000
+            00000000
+        "#}), 'b');
+    }
+
+    #[test]
+    fn test_red_spot_isolated_floe() {
+        assert_eq!(spot_at_center(indoc! {r#"
+            00000000
+            00000000
+            00001000
+            002R3000
+            00000000
+            00000000
+            00000000
+            00000000
+        "#}), 'r');
+    }
+
+    #[test]
+    fn test_red_spot_two_groups() {
+        assert_eq!(spot_at_center(indoc! {r#"
+            00000000
+            00000000
+            00001000
+            002R3000
+            00010000
+            00000000
+            00000000
+            00000000
+        "#}), 'r');
+    }
+
+    #[test]
+    fn test_white_spots() {
+        // A connected arc of floes
+        assert_eq!(spot_at_center(indoc! {r#"
+            00000000
+            00000000
+            00011000
+            000R1000
+            00000000
+            00000000
+            00000000
+            00000000
+        "#}), 'w');
+
+        // Fully surrounded by floes
+        assert_eq!(spot_at_center(indoc! {r#"
+            00000000
+            00000000
+            00011000
+            001R1000
+            00011000
+            00000000
+            00000000
+            00000000
+        "#}), 'w');
+
+        // Fully enclosed by water
+        assert_eq!(spot_at_center(indoc! {r#"
+            00000000
+            00000000
+            00000000
+            000R0000
+            00000000
+            00000000
+            00000000
+            0000000B
+        "#}), 'w');
+    }
+
+    #[test]
+    fn test_board_edges() {
+        // Neighbors outside of the board count as water
+        let board: Board = indoc! {r#"
+            R1000000
+            10000000
+            00000000
+            00000000
+            00000000
+            00000000
+            00000000
+            0000000B
+        "#}.parse().unwrap();
+        assert_eq!(spot_name(&board, Vec2::<Direct>::new(0, 0).into()), 'w');
+        assert_eq!(spot_name(&board, Vec2::<Direct>::new(7, 7).into()), 'w');
+
+        let board: Board = indoc! {r#"
+            00000000
+            00000000
+            00000000
+            00000001
+            0000000B
+            00000000
+            00000000
+            00000000
+        "#}.parse().unwrap();
+        assert_eq!(spot_name(&board, Vec2::<Direct>::new(7, 4).into()), 'y');
+    }
+}
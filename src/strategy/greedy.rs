@@ -0,0 +1,12 @@
+use crate::client::GameClientDelegate;
+use crate::game::{Move, State, Team};
+use crate::session::fallback_move;
+
+/// Moves onto the floe with the most fish, like the fallback move.
+pub struct GreedyStrategy;
+
+impl GameClientDelegate for GreedyStrategy {
+    fn request_move(&mut self, state: &State, _my_team: Team) -> Move {
+        fallback_move(state).expect("No move available")
+    }
+}
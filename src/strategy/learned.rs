@@ -0,0 +1,54 @@
+use crate::client::GameClientDelegate;
+use crate::game::{Move, State, Team};
+use crate::minimax::{new_minimax_with, principal_variation, take_nodes};
+use crate::patterns::{self, PatternTable};
+use crate::session::fallback_move;
+use crate::telemetry::SearchInfo;
+use crate::util::{Error, Result};
+
+/// Searches up to a fixed depth, evaluating positions by fish,
+/// territory and the penguins' patterns in a learned table.
+pub struct PatternStrategy {
+    /// The table to use, the installed one if `None`.
+    table: Option<PatternTable>,
+    depth: i32,
+    last: Option<SearchInfo>,
+}
+
+impl PatternStrategy {
+    /// Creates the strategy using the table installed with `patterns::set_table`,
+    /// failing if there is none rather than searching with an all-zero table.
+    pub fn new(depth: i32) -> Result<Self> {
+        patterns::installed_table().ok_or(Error::NotSet { what: "pattern table" })?;
+        Ok(Self { table: None, depth, last: None })
+    }
+
+    /// Creates the strategy using the given table.
+    pub fn with_table(table: PatternTable, depth: i32) -> Self {
+        Self { table: Some(table), depth, last: None }
+    }
+
+    fn table(&self) -> &PatternTable {
+        self.table.as_ref().or_else(|| patterns::installed_table()).expect("A table is installed before the strategy is created")
+    }
+}
+
+impl GameClientDelegate for PatternStrategy {
+    fn request_move(&mut self, state: &State, my_team: Team) -> Move {
+        let table = self.table();
+        take_nodes();
+        let (m, score) = new_minimax_with(&mut state.clone(), my_team, f32::MIN, f32::MAX, self.depth, self.depth - 1, &|s, my_turn| table.evaluate(s, my_turn));
+        self.last = Some(SearchInfo {
+            depth: Some(self.depth as u32),
+            nodes: take_nodes(),
+            score: Some(score),
+            pv: principal_variation(state.turn()),
+            tt_hit_rate: None,
+        });
+        m.or_else(|| fallback_move(state)).expect("No move available")
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last.clone()
+    }
+}
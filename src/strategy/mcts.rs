@@ -0,0 +1,161 @@
+use std::time::{Duration, Instant};
+
+use log::debug;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::client::GameClientDelegate;
+use crate::game::{Move, State, Team};
+use crate::telemetry::SearchInfo;
+
+/// A node of the search tree.
+struct Node {
+    state: State,
+    /// The move leading here along with the team that made it.
+    action: Option<(Move, Team)>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    /// The summed rewards of the team that made the move leading here.
+    reward: f32,
+}
+
+impl Node {
+    fn new(state: State, action: Option<(Move, Team)>, parent: Option<usize>) -> Self {
+        let untried = if state.is_over() { Vec::new() } else { state.possible_moves() };
+        Self { state, action, parent, children: Vec::new(), untried, visits: 0, reward: 0.0 }
+    }
+}
+
+/// Runs a Monte Carlo tree search, selecting moves with UCT
+/// and rating positions by random playouts to the end.
+pub struct MctsStrategy {
+    budget: Duration,
+    iterations: Option<usize>,
+    exploration: f32,
+    rng: StdRng,
+    last: Option<SearchInfo>,
+}
+
+impl MctsStrategy {
+    /// Creates the strategy, seeded from the OS if no seed is given.
+    pub fn new(budget: Duration, seed: Option<u64>) -> Self {
+        Self {
+            budget,
+            iterations: None,
+            exploration: 1.4,
+            rng: seed.map(StdRng::seed_from_u64).unwrap_or_else(StdRng::from_entropy),
+            last: None,
+        }
+    }
+
+    /// Stops after the given number of playouts, even with time left.
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// Sets how strongly rarely visited moves are preferred.
+    pub fn with_exploration(mut self, exploration: f32) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Picks the child with the best upper confidence bound.
+    fn select(&self, nodes: &[Node], node: &Node) -> usize {
+        let log_visits = (node.visits as f32).ln();
+        let bound = |i: usize| {
+            let child = &nodes[i];
+            child.reward / child.visits as f32 + self.exploration * (log_visits / child.visits as f32).sqrt()
+        };
+        node.children.iter()
+            .copied()
+            .max_by(|&a, &b| bound(a).total_cmp(&bound(b)))
+            .expect("Selected node has children")
+    }
+
+    /// Plays random moves until the game is over.
+    fn playout(&mut self, mut state: State) -> Option<Team> {
+        while !state.is_over() {
+            let m = *state.possible_moves().choose(&mut self.rng).expect("Running game has moves");
+            state.perform(m);
+        }
+        state.winner()
+    }
+}
+
+impl GameClientDelegate for MctsStrategy {
+    fn request_move(&mut self, state: &State, _my_team: Team) -> Move {
+        let start = Instant::now();
+        let mut nodes = vec![Node::new(*state, None, None)];
+        if let [m] = nodes[0].untried[..] {
+            self.last = None;
+            return m;
+        }
+        let mut iterations = 0;
+        let mut max_depth = 0;
+        while start.elapsed() < self.budget && self.iterations.is_none_or(|n| iterations < n) {
+            // Descend through fully expanded nodes
+            let mut current = 0;
+            let mut depth = 0;
+            while nodes[current].untried.is_empty() && !nodes[current].children.is_empty() {
+                current = self.select(&nodes, &nodes[current]);
+                depth += 1;
+            }
+
+            // Expand by one untried move
+            if !nodes[current].untried.is_empty() {
+                let i = self.rng.gen_range(0..nodes[current].untried.len());
+                let m = nodes[current].untried.swap_remove(i);
+                let parent = &nodes[current].state;
+                let child = Node::new(parent.child(m), Some((m, parent.current_team())), Some(current));
+                nodes.push(child);
+                let index = nodes.len() - 1;
+                nodes[current].children.push(index);
+                current = index;
+                depth += 1;
+            }
+            max_depth = max_depth.max(depth);
+
+            // Rate the position and propagate the result up
+            let winner = self.playout(nodes[current].state);
+            let mut next = Some(current);
+            while let Some(i) = next {
+                let node = &mut nodes[i];
+                node.visits += 1;
+                node.reward += match (winner, node.action) {
+                    (None, _) => 0.5,
+                    (Some(winner), Some((_, team))) if winner == team => 1.0,
+                    _ => 0.0,
+                };
+                next = node.parent;
+            }
+            iterations += 1;
+        }
+        debug!("MCTS ran {} playouts", iterations);
+
+        // Follow the most visited moves, the first of which is played
+        let mut pv = Vec::new();
+        let mut current = &nodes[0];
+        while let Some(child) = current.children.iter().map(|&i| &nodes[i]).max_by_key(|child| child.visits) {
+            pv.extend(child.action.map(|(m, _)| m));
+            current = child;
+        }
+        let best = nodes[0].children.iter().map(|&i| &nodes[i]).max_by_key(|child| child.visits);
+        self.last = Some(SearchInfo {
+            depth: Some(max_depth),
+            nodes: nodes.len() as u64,
+            score: best.map(|child| child.reward / child.visits as f32),
+            pv,
+            tt_hit_rate: None,
+        });
+        best.and_then(|child| child.action)
+            .map(|(m, _)| m)
+            .or_else(|| nodes[0].untried.first().copied())
+            .expect("No move available")
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last.clone()
+    }
+}
@@ -0,0 +1,289 @@
+mod random;
+mod greedy;
+mod search;
+mod mcts;
+mod learned;
+
+pub use random::*;
+pub use greedy::*;
+pub use search::*;
+pub use mcts::*;
+pub use learned::*;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::client::GameClientDelegate;
+use crate::game::State;
+use crate::util::{Error, Result};
+
+/// A bot picking the moves of a client, as selected by a `StrategySpec`.
+pub type Strategy = Box<dyn GameClientDelegate + Send>;
+
+/// A strategy that can be selected by name.
+pub struct StrategyInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The names and descriptions of the options the strategy accepts.
+    pub options: &'static [(&'static str, &'static str)],
+    create: fn(&StrategyOptions, Duration) -> Result<Strategy>,
+}
+
+/// The registry of all strategies.
+pub const STRATEGIES: &[StrategyInfo] = &[
+    StrategyInfo {
+        name: "random",
+        description: "Picks a legal move at random.",
+        options: &[("seed", "seeds the random generator")],
+        create: |options, _| Ok(Box::new(RandomStrategy::new(options.get("seed")?))),
+    },
+    StrategyInfo {
+        name: "greedy",
+        description: "Moves onto the floe with the most fish, without search.",
+        options: &[],
+        create: |_, _| Ok(Box::new(GreedyStrategy)),
+    },
+    StrategyInfo {
+        name: "minimax",
+        description: "Searches with alpha-beta pruning up to a fixed depth.",
+        options: &[("depth", "the search depth (default 3)")],
+        create: |options, _| Ok(Box::new(MinimaxStrategy::new(options.get_positive("depth")?.unwrap_or(3)))),
+    },
+    StrategyInfo {
+        name: "dynmax",
+        description: "Deepens the minimax search iteratively until the time budget is used up.",
+        options: &[
+            ("millis", "the time budget per move (default: the configured budget)"),
+            ("depth", "the depth at which to stop deepening (default 30)"),
+        ],
+        create: |options, budget| Ok(Box::new(DynMaxStrategy::new(
+            options.get("millis")?.map(Duration::from_millis).unwrap_or(budget),
+            options.get_positive("depth")?.unwrap_or(crate::minimax::DEFAULT_MAX_DEPTH),
+        ))),
+    },
+    StrategyInfo {
+        name: "mcts",
+        description: "Runs a Monte Carlo tree search with random playouts until the time budget is used up.",
+        options: &[
+            ("millis", "the time budget per move (default: the configured budget)"),
+            ("iterations", "stops after this many playouts, even with time left"),
+            ("exploration", "the UCT exploration constant (default 1.4)"),
+            ("seed", "seeds the random playouts"),
+        ],
+        create: |options, budget| {
+            let mut mcts = MctsStrategy::new(options.get("millis")?.map(Duration::from_millis).unwrap_or(budget), options.get("seed")?);
+            if let Some(iterations) = options.get_positive("iterations")? {
+                mcts = mcts.with_iterations(iterations);
+            }
+            if let Some(exploration) = options.get("exploration")? {
+                mcts = mcts.with_exploration(exploration);
+            }
+            Ok(Box::new(mcts))
+        },
+    },
+    StrategyInfo {
+        name: "patterns",
+        description: "Searches up to a fixed depth, evaluating fish, territory and a learned pattern table.",
+        options: &[
+            ("table", "the pattern table to load (required unless one is given by --patterns)"),
+            ("depth", "the search depth (default 2)"),
+        ],
+        create: |options, _| {
+            let depth = options.get_positive("depth")?.unwrap_or(2);
+            Ok(Box::new(match options.value("table") {
+                Some(path) => PatternStrategy::with_table(crate::patterns::PatternTable::load(path)?, depth),
+                None => PatternStrategy::new(depth)?,
+            }))
+        },
+    },
+];
+
+/// Looks up the strategy with the given name.
+pub fn find_strategy(name: &str) -> Result<&'static StrategyInfo> {
+    STRATEGIES.iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| Error::UnknownVariant { kind: "strategy", found: name.to_owned() })
+}
+
+/// The `key=value` options given to a strategy.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StrategyOptions(Vec<(String, String)>);
+
+impl StrategyOptions {
+    /// Fetches the raw value of an option.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.0.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Fetches and parses the value of an option.
+    pub fn get<T>(&self, key: &str) -> Result<Option<T>> where T: FromStr, T::Err: Into<Error> {
+        self.value(key)
+            .map(|value| value.parse().map_err(|e: T::Err| e.into().context(format!("Invalid option {}={}", key, value))))
+            .transpose()
+    }
+
+    /// Fetches a numeric option that has to be at least 1.
+    fn get_positive<T>(&self, key: &str) -> Result<Option<T>> where T: FromStr + PartialOrd + From<u8> + fmt::Display, T::Err: Into<Error> {
+        match self.get::<T>(key)? {
+            Some(value) if value < T::from(1) => Err(Error::InvalidNotation { expected: "a positive number", found: value.to_string() }
+                .context(format!("Invalid option {}={}", key, value))),
+            value => Ok(value),
+        }
+    }
+}
+
+/// A strategy name with options, written like `minimax:depth=4`
+/// or `mcts:millis=1000,seed=7`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategySpec {
+    pub name: String,
+    pub options: StrategyOptions,
+}
+
+impl StrategySpec {
+    /// The registered strategy of this spec.
+    pub fn info(&self) -> Result<&'static StrategyInfo> {
+        find_strategy(&self.name)
+    }
+
+    /// Creates the strategy, giving time-bound ones the
+    /// budget unless the spec overrides it.
+    pub fn create(&self, time_budget: Duration) -> Result<Strategy> {
+        let info = self.info()?;
+        (info.create)(&self.options, time_budget)
+            .map_err(|e| e.context(format!("Could not create strategy {}", self)))
+    }
+}
+
+impl Default for StrategySpec {
+    fn default() -> Self {
+        Self { name: "dynmax".to_owned(), options: StrategyOptions::default() }
+    }
+}
+
+impl fmt::Display for StrategySpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        for (i, (key, value)) in self.options.0.iter().enumerate() {
+            write!(f, "{}{}={}", if i == 0 { ':' } else { ',' }, key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for StrategySpec {
+    type Err = Error;
+
+    /// Parses a spec, checking that the strategy and its options exist.
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, raw_options) = s.split_once(':').unwrap_or((s, ""));
+        let info = find_strategy(name.trim())?;
+        let mut options = Vec::new();
+        for option in raw_options.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            let (key, value) = option.split_once('=')
+                .ok_or_else(|| Error::InvalidNotation { expected: "key=value", found: option.to_owned() })?;
+            let key = key.trim();
+            if !info.options.iter().any(|&(k, _)| k == key) {
+                return Err(Error::UnknownVariant { kind: "strategy option", found: key.to_owned() }
+                    .context(format!("Invalid options for strategy {}", info.name)));
+            }
+            options.push((key.to_owned(), value.trim().to_owned()));
+        }
+        Ok(Self { name: info.name.to_owned(), options: StrategyOptions(options) })
+    }
+}
+
+/// Plays a game between two delegates starting from the given state
+/// without a server, e.g. to pit strategies against each other.
+/// Returns the final state, or fails if a delegate picks an illegal move.
+pub fn play_game(mut state: State, one: &mut dyn GameClientDelegate, two: &mut dyn GameClientDelegate) -> Result<State> {
+    let mut players: [&mut dyn GameClientDelegate; 2] = [one, two];
+    for player in players.iter_mut() {
+        player.on_game_start();
+        player.on_update_state(&state);
+    }
+    while !state.is_over() {
+        let team = state.current_team();
+        let player = &mut players[team.index()];
+        let start = Instant::now();
+        let m = player.request_move(&state, team);
+        state.check_move(m)?;
+        player.on_move_sent(&state, m, start.elapsed(), false);
+        state.perform(m);
+        for player in players.iter_mut() {
+            player.on_update_state(&state);
+        }
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+    use std::time::Duration;
+
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::game::{Board, State, Team};
+    use crate::patterns::PatternTable;
+
+    use super::{STRATEGIES, StrategySpec, play_game};
+
+    fn random_state(seed: u64) -> State {
+        State::new(Board::random(&mut StdRng::seed_from_u64(seed)), 0, [0, 0], None, Team::One)
+    }
+
+    #[test]
+    fn test_spec() {
+        let spec: StrategySpec = "mcts:millis=100, seed=3".parse().unwrap();
+        assert_eq!(spec.name, "mcts");
+        assert_eq!(spec.options.value("seed"), Some("3"));
+        assert_eq!(spec.to_string(), "mcts:millis=100,seed=3");
+        assert_eq!(spec.to_string().parse::<StrategySpec>().unwrap(), spec);
+        assert_eq!("greedy".parse::<StrategySpec>().unwrap().to_string(), "greedy");
+
+        assert_eq!("deep_blue".parse::<StrategySpec>().unwrap_err().to_string(), "Unknown strategy 'deep_blue'");
+        assert_eq!("minimax:width=3".parse::<StrategySpec>().unwrap_err().to_string(), "Invalid options for strategy minimax: Unknown strategy option 'width'");
+        assert!("minimax:depth".parse::<StrategySpec>().is_err());
+
+        let budget = Duration::from_millis(10);
+        assert!("minimax:depth=0".parse::<StrategySpec>().unwrap().create(budget).err().unwrap().to_string().contains("Expected a positive number, found '0'"));
+        assert!("random:seed=x".parse::<StrategySpec>().unwrap().create(budget).is_err());
+        assert!("patterns:table=/nonexistent".parse::<StrategySpec>().unwrap().create(budget).is_err());
+        assert!("patterns".parse::<StrategySpec>().unwrap().create(budget).err().unwrap().to_string().contains("The pattern table is not set"));
+    }
+
+    #[test]
+    fn test_strategies_play_legal_games() {
+        let table = env::temp_dir().join(format!("patterns-{}.txt", process::id()));
+        fs::write(&table, PatternTable::new(false).to_string()).unwrap();
+        let patterns = format!("patterns:depth=1,table={}", table.display());
+        let specs = ["random:seed=1", "greedy", "minimax:depth=1", "dynmax:millis=20,depth=2", "mcts:iterations=50,seed=1", &patterns];
+        assert_eq!(specs.len(), STRATEGIES.len());
+        let budget = Duration::from_millis(20);
+        for (i, spec) in specs.iter().enumerate() {
+            let mut strategy = spec.parse::<StrategySpec>().unwrap().create(budget).unwrap();
+            let mut opponent = "random:seed=2".parse::<StrategySpec>().unwrap().create(budget).unwrap();
+            let state = play_game(random_state(i as u64), strategy.as_mut(), opponent.as_mut())
+                .unwrap_or_else(|e| panic!("{} played illegally: {}", spec, e));
+            assert!(state.is_over(), "{} did not finish", spec);
+        }
+        fs::remove_file(table).unwrap();
+    }
+
+    #[test]
+    fn test_greedy_beats_random() {
+        let budget = Duration::ZERO;
+        let mut wins = 0;
+        for seed in 0..10 {
+            let mut greedy = "greedy".parse::<StrategySpec>().unwrap().create(budget).unwrap();
+            let mut random = format!("random:seed={}", seed).parse::<StrategySpec>().unwrap().create(budget).unwrap();
+            let state = play_game(random_state(seed), greedy.as_mut(), random.as_mut()).unwrap();
+            if state.winner() == Some(Team::One) {
+                wins += 1;
+            }
+        }
+        assert!(wins >= 7, "greedy won only {} of 10 games", wins);
+    }
+}
@@ -0,0 +1,24 @@
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::client::GameClientDelegate;
+use crate::game::{Move, State, Team};
+
+/// Picks a legal move uniformly at random.
+pub struct RandomStrategy {
+    rng: StdRng,
+}
+
+impl RandomStrategy {
+    /// Creates the strategy, seeded from the OS if no seed is given.
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            rng: seed.map(StdRng::seed_from_u64).unwrap_or_else(StdRng::from_entropy),
+        }
+    }
+}
+
+impl GameClientDelegate for RandomStrategy {
+    fn request_move(&mut self, state: &State, _my_team: Team) -> Move {
+        *state.possible_moves().choose(&mut self.rng).expect("No move available")
+    }
+}
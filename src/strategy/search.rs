@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use crate::client::GameClientDelegate;
+use crate::game::{Move, State, Team};
+use crate::minimax::{dyn_max_search, new_minimax, principal_variation, take_nodes};
+use crate::session::fallback_move;
+use crate::telemetry::SearchInfo;
+
+/// Searches with alpha-beta pruning up to a fixed depth.
+pub struct MinimaxStrategy {
+    depth: i32,
+    last: Option<SearchInfo>,
+}
+
+impl MinimaxStrategy {
+    pub fn new(depth: i32) -> Self {
+        Self { depth, last: None }
+    }
+}
+
+impl GameClientDelegate for MinimaxStrategy {
+    fn request_move(&mut self, state: &State, my_team: Team) -> Move {
+        take_nodes();
+        let (m, score) = new_minimax(&mut state.clone(), my_team, f32::MIN, f32::MAX, self.depth, self.depth - 1);
+        self.last = Some(SearchInfo {
+            depth: Some(self.depth as u32),
+            nodes: take_nodes(),
+            score: Some(score),
+            pv: principal_variation(state.turn()),
+            tt_hit_rate: None,
+        });
+        m.or_else(|| fallback_move(state)).expect("No move available")
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last.clone()
+    }
+}
+
+/// Deepens the minimax search iteratively until the
+/// time budget is used up or the maximum depth is reached.
+pub struct DynMaxStrategy {
+    budget: Duration,
+    max_depth: i32,
+    last: Option<SearchInfo>,
+}
+
+impl DynMaxStrategy {
+    pub fn new(budget: Duration, max_depth: i32) -> Self {
+        Self { budget, max_depth, last: None }
+    }
+}
+
+impl GameClientDelegate for DynMaxStrategy {
+    fn request_move(&mut self, state: &State, my_team: Team) -> Move {
+        let (m, info) = dyn_max_search(*state, my_team, self.budget, self.max_depth);
+        self.last = Some(info);
+        // Without a finished iteration there is no move, so fall back to the greedy one
+        m.or_else(|| fallback_move(state)).expect("No move available")
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last.clone()
+    }
+}
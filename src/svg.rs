@@ -0,0 +1,185 @@
+use std::fmt::Write;
+
+use crate::analysis::Distances;
+use crate::game::{Board, Move, State, Team, Vec2, Doubled, BOARD_FIELDS, BOARD_SIZE, square_name};
+
+/// The colors of the teams.
+const TEAM_COLORS: [&str; 2] = ["#d1383d", "#2f6fc4"];
+const WATER_COLOR: &str = "#a9d3ef";
+const FLOE_COLOR: &str = "#f5f9fc";
+const OUTLINE_COLOR: &str = "#6c8ba3";
+const ARROW_COLOR: &str = "#2b2b2b";
+
+/// Draws game states as SVG images, with hexagons showing the fish
+/// counts and penguins, optional arrows for moves and an optional
+/// heatmap overlay of per-field values.
+#[derive(Debug, Clone)]
+pub struct SvgRenderer {
+    /// The radius of a hexagon in pixels.
+    radius: f32,
+    labels: bool,
+    arrows: Vec<Move>,
+    heatmap: Option<[Option<f32>; BOARD_FIELDS]>,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self { radius: 30.0, labels: false, arrows: Vec::new(), heatmap: None }
+    }
+}
+
+impl SvgRenderer {
+    /// Creates a renderer with a hexagon radius of 30 pixels.
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets the radius of a hexagon in pixels.
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Draws the name of each field.
+    pub fn labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Draws an arrow for the given move.
+    pub fn arrow(mut self, m: Move) -> Self {
+        self.arrows.push(m);
+        self
+    }
+
+    /// Overlays the given values, indexed like the board fields. The values are
+    /// scaled between their minimum and maximum, fields without value are left out.
+    pub fn heatmap(mut self, values: [Option<f32>; BOARD_FIELDS]) -> Self {
+        self.heatmap = Some(values);
+        self
+    }
+
+    /// Overlays the number of moves needed to reach each field.
+    pub fn distances(self, distances: &Distances) -> Self {
+        let values = std::array::from_fn(|i| {
+            let steps = distances.steps_at(i);
+            (steps != Distances::UNREACHABLE).then_some(steps as f32)
+        });
+        self.heatmap(values)
+    }
+
+    /// The center of the given field in pixels.
+    fn center(&self, coords: Vec2<Doubled>) -> (f32, f32) {
+        let width = 3f32.sqrt() * self.radius;
+        (self.radius + (coords.x + 1) as f32 * width / 2.0, 2.0 * self.radius + coords.y as f32 * 1.5 * self.radius)
+    }
+
+    fn hexagon(&self, coords: Vec2<Doubled>) -> String {
+        let (cx, cy) = self.center(coords);
+        (0..6).map(|i| {
+            let angle = (60.0 * i as f32 + 30.0).to_radians();
+            format!("{:.1},{:.1}", cx + self.radius * angle.cos(), cy + self.radius * angle.sin())
+        }).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Draws the state as an SVG document.
+    pub fn render(&self, state: &State) -> String {
+        let board = state.board();
+        let width = 3f32.sqrt() * self.radius * (BOARD_SIZE as f32 + 0.5) + 2.0 * self.radius;
+        let height = 1.5 * self.radius * BOARD_SIZE as f32 + 2.5 * self.radius;
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}" font-family="sans-serif">"#, width, height, width, height);
+        let _ = writeln!(svg, r#"<defs><marker id="arrow" viewBox="0 0 10 10" refX="8" refY="5" markerWidth="6" markerHeight="6" orient="auto-start-reverse"><path d="M 0 0 L 10 5 L 0 10 z" fill="{}"/></marker></defs>"#, ARROW_COLOR);
+        let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="{}"/>"#, WATER_COLOR);
+        let _ = writeln!(svg, r#"<text x="{:.0}" y="{:.0}" font-size="{:.0}">Turn {}, {}: {} fish, {}: {} fish</text>"#,
+            self.radius / 2.0, self.radius * 0.8, self.radius / 2.0, state.turn(),
+            Team::One, state.fish(Team::One), Team::Two, state.fish(Team::Two));
+
+        for (coords, field) in board.fields() {
+            let fill = if field.fish() > 0 || field.penguin().is_some() { FLOE_COLOR } else { WATER_COLOR };
+            let _ = writeln!(svg, r#"<polygon class="field" points="{}" fill="{}" stroke="{}"/>"#, self.hexagon(coords), fill, OUTLINE_COLOR);
+        }
+        self.render_heatmap(&mut svg);
+        for (coords, field) in board.fields() {
+            let (cx, cy) = self.center(coords);
+            if let Some(team) = field.penguin() {
+                let _ = writeln!(svg, r#"<circle class="penguin" cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"/>"#, cx, cy, self.radius * 0.55, TEAM_COLORS[team.index()]);
+                let _ = writeln!(svg, r#"<text x="{:.1}" y="{:.1}" font-size="{:.0}" text-anchor="middle" dominant-baseline="central" fill="white">{}</text>"#, cx, cy, self.radius * 0.6, team.letter());
+            } else if field.fish() > 0 {
+                let _ = writeln!(svg, r#"<text x="{:.1}" y="{:.1}" font-size="{:.0}" text-anchor="middle" dominant-baseline="central">{}</text>"#, cx, cy, self.radius * 0.7, field.fish());
+            }
+            if self.labels {
+                if let Some(name) = square_name(coords) {
+                    let _ = writeln!(svg, r#"<text x="{:.1}" y="{:.1}" font-size="{:.0}" text-anchor="middle" fill="{}">{}</text>"#, cx, cy + self.radius * 0.75, self.radius * 0.3, OUTLINE_COLOR, name);
+                }
+            }
+        }
+        for m in &self.arrows {
+            let (tx, ty) = self.center(m.to());
+            match m.from() {
+                Some(from) => {
+                    let (fx, fy) = self.center(from);
+                    let _ = writeln!(svg, r#"<line class="move" x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="{:.1}" marker-end="url(#arrow)"/>"#, fx, fy, tx, ty, ARROW_COLOR, self.radius / 10.0);
+                },
+                None => {
+                    let _ = writeln!(svg, r#"<circle class="move" cx="{:.1}" cy="{:.1}" r="{:.1}" fill="none" stroke="{}" stroke-width="{:.1}"/>"#, tx, ty, self.radius * 0.8, ARROW_COLOR, self.radius / 10.0);
+                },
+            }
+        }
+        svg += "</svg>\n";
+        svg
+    }
+
+    fn render_heatmap(&self, svg: &mut String) {
+        let Some(values) = &self.heatmap else { return };
+        let present = || values.iter().flatten().copied();
+        let (min, max) = (present().fold(f32::INFINITY, f32::min), present().fold(f32::NEG_INFINITY, f32::max));
+        for (i, value) in values.iter().enumerate() {
+            let Some(value) = value else { continue };
+            let t = if max > min { (value - min) / (max - min) } else { 0.0 };
+            // Low values are drawn in yellow, high values in red
+            let green = (220.0 * (1.0 - t)) as u8;
+            let coords: Vec2<Doubled> = Board::coords_for(i).into();
+            let _ = writeln!(svg, r#"<polygon class="heat" points="{}" fill="rgb(230,{},40)" fill-opacity="0.45"><title>{}: {}</title></polygon>"#,
+                self.hexagon(coords), green, square_name(coords).unwrap_or_default(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{analysis::Distances, game::{Move, State, Team, Vec2, Doubled}};
+
+    use super::SvgRenderer;
+
+    fn state() -> State {
+        "R1000000/02000000/00000000/00000000/00000000/00000000/00000000/0000000B B 2 1:1 h8".parse().unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let svg = SvgRenderer::new()
+            .labels(true)
+            .arrow(Move::between(Vec2::<Doubled>::new(0, 0), Vec2::<Doubled>::new(2, 0)))
+            .arrow(Move::placing(Vec2::<Doubled>::new(3, 1)))
+            .render(&state());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches(r#"class="field""#).count(), 64);
+        assert_eq!(svg.matches(r#"class="penguin""#).count(), 2);
+        assert_eq!(svg.matches(r#"class="move""#).count(), 2);
+        assert_eq!(svg.matches(r#"class="heat""#).count(), 0);
+        assert!(svg.contains(">a1</text>"));
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let state = state();
+        let svg = SvgRenderer::new()
+            .distances(&Distances::of_team(state.board(), Team::One))
+            .render(&state);
+        // The penguin on a1 reaches b1 in one and b2 in two moves
+        assert_eq!(svg.matches(r#"class="heat""#).count(), 3);
+        assert!(svg.contains("<title>a1: 0</title>"));
+        assert!(svg.contains("<title>b1: 1</title>"));
+        assert!(svg.contains("<title>b2: 2</title>"));
+    }
+}
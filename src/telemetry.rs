@@ -0,0 +1,478 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::client::GameClientDelegate;
+use crate::game::{Move, State, Team};
+use crate::protocol::GameResult;
+use crate::util::{Error, Result};
+
+/// Details about the search for a move, reported by delegates that search.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchInfo {
+    /// The depth of the deepest finished search in plies.
+    pub depth: Option<u32>,
+    /// The positions visited while searching.
+    pub nodes: u64,
+    /// The score of the chosen move from the view of the searching team.
+    pub score: Option<f32>,
+    /// The line of best play, starting with the chosen move.
+    pub pv: Vec<Move>,
+    /// The share of transposition table probes that hit, `None`
+    /// for searches without a transposition table.
+    pub tt_hit_rate: Option<f64>,
+}
+
+/// The outcome of a game for our team.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// The statistics of a single move.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveRecord {
+    /// The number of the game within the client run.
+    pub game: u32,
+    pub strategy: Option<String>,
+    pub turn: usize,
+    pub team: String,
+    #[serde(rename = "move")]
+    pub chosen: String,
+    pub legal_moves: usize,
+    /// The time from the move request until the move was sent.
+    pub time_ms: f64,
+    /// Whether the client sent a fallback move in place of the delegate's,
+    /// in which case the search details describe the discarded search.
+    #[serde(default)]
+    pub fallback: bool,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    /// The nodes searched per second.
+    pub nps: Option<f64>,
+    pub score: Option<f32>,
+    pub pv: Vec<String>,
+    pub tt_hit_rate: Option<f64>,
+}
+
+/// The statistics of a finished game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    /// The number of the game within the client run.
+    pub game: u32,
+    pub strategy: Option<String>,
+    pub team: Option<String>,
+    /// The outcome for our team, `None` if our team is unknown.
+    pub result: Option<Outcome>,
+    /// Our fish minus the opponent's fish at the end of the game.
+    pub fish_margin: Option<i64>,
+    pub moves: usize,
+    /// The time spent computing our moves.
+    pub total_time_ms: f64,
+    pub nodes: u64,
+}
+
+/// A line of the telemetry log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Record {
+    Move(MoveRecord),
+    Game(GameRecord),
+}
+
+impl Record {
+    /// The strategy that played the move or game.
+    pub fn strategy(&self) -> Option<&str> {
+        match self {
+            Self::Move(record) => record.strategy.as_deref(),
+            Self::Game(record) => record.strategy.as_deref(),
+        }
+    }
+
+    /// Reads the records of a JSON Lines log, skipping empty lines.
+    pub fn read_all(reader: impl BufRead) -> Result<Vec<Self>> {
+        let mut records = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line)
+                .map_err(|e| Error::from(e).context(format!("Invalid record on line {}", i + 1)))?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+/// Aggregated statistics over many moves and games.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub games: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub fish_margin: i64,
+    /// The number of games with a known fish margin.
+    pub margins: usize,
+    pub moves: usize,
+    /// The moves replaced by a fallback move.
+    pub fallbacks: usize,
+    pub time_ms: f64,
+    pub max_time_ms: f64,
+    /// The summed depths of the moves that report one.
+    pub depth: u64,
+    pub searched_moves: usize,
+    pub nodes: u64,
+    /// The time spent on the moves that report nodes.
+    pub search_time_ms: f64,
+}
+
+impl Summary {
+    /// Summarizes the given records.
+    pub fn of<'a>(records: impl IntoIterator<Item=&'a Record>) -> Self {
+        let mut summary = Self::default();
+        for record in records {
+            summary.add(record);
+        }
+        summary
+    }
+
+    /// Summarizes the given records per strategy.
+    pub fn by_strategy<'a>(records: impl IntoIterator<Item=&'a Record>) -> BTreeMap<String, Self> {
+        let mut summaries = BTreeMap::<String, Self>::new();
+        for record in records {
+            let strategy = record.strategy().unwrap_or("unknown").to_owned();
+            summaries.entry(strategy).or_default().add(record);
+        }
+        summaries
+    }
+
+    /// Adds a record to the summary.
+    pub fn add(&mut self, record: &Record) {
+        match record {
+            Record::Move(m) => {
+                self.moves += 1;
+                self.fallbacks += m.fallback as usize;
+                self.time_ms += m.time_ms;
+                self.max_time_ms = self.max_time_ms.max(m.time_ms);
+                if let Some(depth) = m.depth {
+                    self.depth += depth as u64;
+                    self.searched_moves += 1;
+                }
+                if let Some(nodes) = m.nodes {
+                    self.nodes += nodes;
+                    self.search_time_ms += m.time_ms;
+                }
+            },
+            Record::Game(g) => {
+                self.games += 1;
+                match g.result {
+                    Some(Outcome::Win) => self.wins += 1,
+                    Some(Outcome::Loss) => self.losses += 1,
+                    Some(Outcome::Draw) => self.draws += 1,
+                    None => (),
+                }
+                if let Some(margin) = g.fish_margin {
+                    self.fish_margin += margin;
+                    self.margins += 1;
+                }
+            },
+        }
+    }
+
+    /// The share of decided and drawn games that were won, counting draws as half.
+    pub fn score(&self) -> Option<f64> {
+        let played = self.wins + self.losses + self.draws;
+        (played > 0).then(|| (self.wins as f64 + self.draws as f64 / 2.0) / played as f64)
+    }
+
+    /// The nodes searched per second over all searching moves.
+    pub fn nps(&self) -> Option<f64> {
+        (self.search_time_ms > 0.0).then(|| self.nodes as f64 * 1000.0 / self.search_time_ms)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} game(s): {} won, {} lost, {} drawn", self.games, self.wins, self.losses, self.draws)?;
+        if let Some(score) = self.score() {
+            write!(f, " ({:.1}% score)", score * 100.0)?;
+        }
+        if self.margins > 0 {
+            write!(f, ", fish margin {:+.1}", self.fish_margin as f64 / self.margins as f64)?;
+        }
+        if self.moves > 0 {
+            write!(f, "; {} move(s), {:.0} ms avg, {:.0} ms max", self.moves, self.time_ms / self.moves as f64, self.max_time_ms)?;
+        }
+        if self.fallbacks > 0 {
+            write!(f, ", {} fallback(s)", self.fallbacks)?;
+        }
+        if self.searched_moves > 0 {
+            write!(f, ", depth {:.1} avg", self.depth as f64 / self.searched_moves as f64)?;
+        }
+        if let Some(nps) = self.nps() {
+            write!(f, ", {} nodes, {:.0} nps", self.nodes, nps)?;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a delegate to record statistics about each move and game,
+/// writing them as JSON Lines and logging a summary after each game.
+pub struct Telemetry<D> {
+    delegate: D,
+    out: Option<Box<dyn Write + Send>>,
+    strategy: Option<String>,
+    game: u32,
+    team: Option<Team>,
+    state: Option<State>,
+    /// The search details of the last move request, kept until the move is sent.
+    search: Option<SearchInfo>,
+    records: Vec<Record>,
+}
+
+impl<D> Telemetry<D> where D: GameClientDelegate {
+    pub fn new(delegate: D) -> Self {
+        Self {
+            delegate,
+            out: None,
+            strategy: None,
+            game: 0,
+            team: None,
+            state: None,
+            search: None,
+            records: Vec::new(),
+        }
+    }
+
+    /// Writes the records to the given output, one JSON object per line.
+    pub fn with_output(mut self, out: impl Write + Send + 'static) -> Self {
+        self.out = Some(Box::new(out));
+        self
+    }
+
+    /// Labels the records with the name of the strategy.
+    pub fn with_strategy(mut self, strategy: impl Into<String>) -> Self {
+        self.strategy = Some(strategy.into());
+        self
+    }
+
+    /// The wrapped delegate.
+    pub fn delegate(&self) -> &D { &self.delegate }
+
+    fn record(&mut self, record: Record) {
+        if let Some(out) = &mut self.out {
+            let written = serde_json::to_writer(&mut *out, &record)
+                .map_err(io::Error::from)
+                .and_then(|_| writeln!(out))
+                .and_then(|_| out.flush());
+            if let Err(e) = written {
+                // Telemetry must not cost us the game, so stop recording instead
+                warn!("Could not write telemetry, disabling it: {}", e);
+                self.out = None;
+            }
+        }
+        self.records.push(record);
+    }
+}
+
+impl<D> GameClientDelegate for Telemetry<D> where D: GameClientDelegate {
+    fn on_update_state(&mut self, state: &State) {
+        self.state = Some(*state);
+        self.delegate.on_update_state(state);
+    }
+
+    fn on_game_start(&mut self) {
+        self.game += 1;
+        self.state = None;
+        self.records.clear();
+        self.delegate.on_game_start();
+    }
+
+    fn on_game_end(&mut self, result: &GameResult) {
+        self.delegate.on_game_end(result);
+        let summary = Summary::of(&self.records);
+        let fish_margin = self.team.zip(self.state).map(|(team, state)| state.fish(team) as i64 - state.fish(team.opponent()) as i64);
+        let record = GameRecord {
+            game: self.game,
+            strategy: self.strategy.clone(),
+            team: self.team.map(|t| t.to_string()),
+            result: self.team.map(|team| match result.winner() {
+                Some(winner) if winner.team() == team => Outcome::Win,
+                Some(_) => Outcome::Loss,
+                None => Outcome::Draw,
+            }),
+            fish_margin,
+            moves: summary.moves,
+            total_time_ms: summary.time_ms,
+            nodes: summary.nodes,
+        };
+        self.record(Record::Game(record));
+        info!("Game {}: {}", self.game, Summary::of(&self.records));
+    }
+
+    fn on_welcome(&mut self, team: Team) {
+        self.team = Some(team);
+        self.delegate.on_welcome(team);
+    }
+
+    fn request_move(&mut self, state: &State, my_team: Team) -> Move {
+        // Cleared first so that a panicking delegate leaves no stale details
+        self.search = None;
+        let m = self.delegate.request_move(state, my_team);
+        self.search = self.delegate.search_info();
+        m
+    }
+
+    fn on_move_sent(&mut self, state: &State, m: Move, duration: Duration, fallback: bool) {
+        let team = state.current_team();
+        self.team.get_or_insert(team);
+        let time_ms = duration.as_secs_f64() * 1000.0;
+        let search = self.search.take();
+        let nodes = search.as_ref().map(|s| s.nodes);
+        let record = MoveRecord {
+            game: self.game,
+            strategy: self.strategy.clone(),
+            turn: state.turn(),
+            team: team.to_string(),
+            chosen: m.to_string(),
+            legal_moves: state.possible_moves().len(),
+            time_ms,
+            fallback,
+            depth: search.as_ref().and_then(|s| s.depth),
+            nodes,
+            nps: nodes.filter(|_| time_ms > 0.0).map(|n| n as f64 * 1000.0 / time_ms),
+            // The score and line belong to the delegate's move, not to a fallback
+            score: search.as_ref().filter(|_| !fallback).and_then(|s| s.score),
+            pv: search.as_ref().filter(|_| !fallback).map(|s| s.pv.iter().map(|m| m.to_string()).collect()).unwrap_or_default(),
+            tt_hit_rate: search.as_ref().and_then(|s| s.tt_hit_rate),
+        };
+        if fallback {
+            warn!("Sent fallback move {} after {:.0} ms", record.chosen, time_ms);
+        } else {
+            debug!("Move {} after {:.0} ms, depth {:?}, score {:?}, pv {}", record.chosen, time_ms, record.depth, record.score, record.pv.join(" "));
+        }
+        self.record(Record::Move(record));
+        self.delegate.on_move_sent(state, m, duration, fallback);
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.delegate.search_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::client::GameClientDelegate;
+    use crate::game::{Board, State, Team};
+    use crate::protocol::{GameResult, Player, ScoreDefinition};
+    use crate::strategy::{StrategySpec, play_game};
+
+    use super::{Outcome, Record, Summary, Telemetry};
+
+    /// An output that can be inspected after handing it to the telemetry.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.0.lock().unwrap().write(buf) }
+
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn test_records() {
+        let out = Shared::default();
+        let strategy = |s: &str| s.parse::<StrategySpec>().unwrap().create(Duration::from_millis(50)).unwrap();
+        let mut one = Telemetry::new(strategy("minimax:depth=1")).with_strategy("minimax:depth=1").with_output(out.clone());
+        let mut two = strategy("random:seed=5");
+        let state = State::new(Board::random(&mut StdRng::seed_from_u64(5)), 0, [0, 0], None, Team::One);
+        let state = play_game(state, &mut one, &mut two).unwrap();
+        let winner = state.winner().map(|team| Player::new(None, team));
+        one.on_game_end(&GameResult::new(ScoreDefinition::new([]), HashMap::new(), winner));
+
+        let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        let records = Record::read_all(text.as_bytes()).unwrap();
+        assert_eq!(records.len(), text.lines().count());
+        let Some(Record::Game(game)) = records.last() else { panic!("No game record in {}", text) };
+        assert_eq!(game.team.as_deref(), Some("ONE"));
+        assert_eq!(game.result, Some(match state.winner() {
+            Some(Team::One) => Outcome::Win,
+            Some(Team::Two) => Outcome::Loss,
+            None => Outcome::Draw,
+        }));
+        assert_eq!(game.fish_margin, Some(state.fish(Team::One) as i64 - state.fish(Team::Two) as i64));
+        assert_eq!(game.moves, records.len() - 1);
+
+        for record in &records[..records.len() - 1] {
+            let Record::Move(m) = record else { panic!("Expected a move record, found {:?}", record) };
+            assert_eq!(m.strategy.as_deref(), Some("minimax:depth=1"));
+            assert_eq!(m.depth, Some(1));
+            assert!(m.nodes.unwrap() > 0);
+            assert_eq!(m.pv.first(), Some(&m.chosen));
+            assert!(!m.fallback);
+            assert!(m.legal_moves > 0);
+            assert_eq!(m.tt_hit_rate, None);
+        }
+
+        let summary = Summary::of(&records);
+        assert_eq!((summary.games, summary.moves), (1, game.moves));
+        assert_eq!(summary.wins + summary.losses + summary.draws, 1);
+        assert_eq!(Summary::by_strategy(&records).keys().collect::<Vec<_>>(), ["minimax:depth=1"]);
+    }
+
+    #[test]
+    fn test_fallback_record() {
+        let out = Shared::default();
+        let strategy = "minimax:depth=1".parse::<StrategySpec>().unwrap().create(Duration::ZERO).unwrap();
+        let mut telemetry = Telemetry::new(strategy).with_output(out.clone());
+        let state = State::new(Board::random(&mut StdRng::seed_from_u64(3)), 0, [0, 0], None, Team::One);
+        let chosen = telemetry.request_move(&state, Team::One);
+        let fallback = *state.possible_moves().iter().find(|&&m| m != chosen).unwrap();
+        telemetry.on_move_sent(&state, fallback, Duration::from_millis(2500), true);
+
+        let records = Record::read_all(&out.0.lock().unwrap()[..]).unwrap();
+        let [Record::Move(m)] = &records[..] else { panic!("Expected a single move record, found {:?}", records) };
+        assert_eq!(m.chosen, fallback.to_string());
+        assert!(m.fallback);
+        assert_eq!(m.time_ms, 2500.0);
+        assert_eq!(m.depth, Some(1));
+        assert_eq!((m.score, m.pv.len()), (None, 0));
+        assert_eq!(Summary::of(&records).fallbacks, 1);
+    }
+
+    #[test]
+    fn test_summary() {
+        let records = Record::read_all(indoc::indoc! {r#"
+            {"type":"move","game":1,"strategy":"a","turn":0,"team":"ONE","move":"a1","legal_moves":8,"time_ms":100.0,"fallback":false,"depth":2,"nodes":1000,"nps":10000.0,"score":1.5,"pv":["a1"],"tt_hit_rate":null}
+            {"type":"move","game":1,"strategy":"a","turn":2,"team":"ONE","move":"b1","legal_moves":7,"time_ms":300.0,"fallback":true,"depth":4,"nodes":3000,"nps":10000.0,"score":2.0,"pv":[],"tt_hit_rate":null}
+            {"type":"game","game":1,"strategy":"a","team":"ONE","result":"win","fish_margin":6,"moves":2,"total_time_ms":400.0,"nodes":4000}
+
+            {"type":"game","game":1,"strategy":"b","team":"TWO","result":"loss","fish_margin":-2,"moves":0,"total_time_ms":0.0,"nodes":0}
+        "#}.as_bytes()).unwrap();
+        let all = Summary::of(&records);
+        assert_eq!((all.games, all.wins, all.losses, all.margins, all.fish_margin), (2, 1, 1, 2, 4));
+        assert_eq!(all.nps(), Some(10000.0));
+        assert_eq!(all.to_string(), "2 game(s): 1 won, 1 lost, 0 drawn (50.0% score), fish margin +2.0; 2 move(s), 200 ms avg, 300 ms max, 1 fallback(s), depth 3.0 avg, 4000 nodes, 10000 nps");
+        let by_strategy = Summary::by_strategy(&records);
+        assert_eq!(by_strategy["a"].score(), Some(1.0));
+        assert_eq!(by_strategy["b"].moves, 0);
+
+        assert!(Record::read_all(&b"{\"type\":\"move\"}\n"[..]).unwrap_err().to_string().contains("line 1"));
+    }
+}
@@ -1,10 +1,14 @@
+use std::fmt;
 use std::io::Error as IoError;
 use std::str::{ParseBoolError, Utf8Error};
 use std::num::{ParseIntError, ParseFloatError};
 use quick_xml::Error as XmlError;
+use serde_json::Error as JsonError;
 use quick_xml::events::attributes::AttrError;
 
-use super::Element;
+use crate::game::MoveError;
+
+use super::{Element, TextPosition};
 
 /// A custom error type that abstracts over
 /// other errors (such as IO/XML errors) and
@@ -18,12 +22,103 @@ pub enum Error {
     ParseBool(ParseBoolError),
     Utf8(Utf8Error),
     Xml(XmlError),
+    Json(JsonError),
+    /// An element that is not known in its context.
     UnknownElement(Element),
-    UnknownVariant(String),
-    InvalidState(String),
-    ServerError(String),
+    /// A name that is none of the known variants of its kind.
+    UnknownVariant { kind: &'static str, found: String },
+    /// An element lacking a required attribute.
+    MissingAttribute { element: String, attribute: String },
+    /// An element lacking a required child element.
+    MissingChild { element: String, child: String },
+    /// An attribute whose value could not be parsed.
+    InvalidAttribute { element: String, attribute: String, value: String, source: Box<Error> },
+    /// Text that does not follow the expected notation.
+    InvalidNotation { expected: &'static str, found: String },
+    /// A list with the wrong number of entries.
+    WrongCount { what: &'static str, expected: usize, found: usize },
+    /// A move that is not legal in the current state.
+    InvalidMove(MoveError),
+    /// A move was sent although none was requested.
+    NoMoveRequested,
+    /// A move was requested before any state was received.
+    NoState,
+    /// A session that cannot be resumed over a new connection.
+    NotResumable,
+    /// The connection ended before any game result was received.
+    NoGameResult,
+    /// A global that can only be set once and already was.
+    AlreadySet { what: &'static str },
+    /// A global that is required but was never set.
+    NotSet { what: &'static str },
+    /// An error reported by the server, with the
+    /// request that caused it if the server sent it along.
+    ServerError { message: String, request: Option<Box<Element>> },
+    /// Invalid XML at the given position.
+    Malformed { position: TextPosition, message: String },
+    /// A message exceeding the size or nesting limits, starting at the given position.
+    LimitExceeded { position: TextPosition, message: String },
+    /// An error with a description of what was being done.
+    Context { context: String, source: Box<Error> },
     Eof,
-    Custom(String)
+}
+
+impl Error {
+    /// Wraps the error with a description of what was being done.
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Self::Context { context: context.into(), source: Box::new(self) }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::ParseInt(e) => write!(f, "Invalid integer: {}", e),
+            Self::ParseFloat(e) => write!(f, "Invalid number: {}", e),
+            Self::ParseBool(e) => write!(f, "Invalid boolean: {}", e),
+            Self::Utf8(e) => write!(f, "Invalid UTF-8: {}", e),
+            Self::Xml(e) => write!(f, "XML error: {}", e),
+            Self::Json(e) => write!(f, "JSON error: {}", e),
+            Self::UnknownElement(element) => write!(f, "Unknown element <{}>: {}", element.name(), element),
+            Self::UnknownVariant { kind, found } => write!(f, "Unknown {} '{}'", kind, found),
+            Self::MissingAttribute { element, attribute } => write!(f, "No attribute '{}' in <{}>", attribute, element),
+            Self::MissingChild { element, child } => write!(f, "No <{}> in <{}>", child, element),
+            Self::InvalidAttribute { element, attribute, value, source } => write!(f, "Invalid attribute {}=\"{}\" in <{}>: {}", attribute, value, element, source),
+            Self::InvalidNotation { expected, found } => write!(f, "Expected {}, found '{}'", expected, found),
+            Self::WrongCount { what, expected, found } => write!(f, "Expected {} {}, found {}", expected, what, found),
+            Self::InvalidMove(e) => write!(f, "Invalid move: {}", e),
+            Self::NoMoveRequested => write!(f, "No move was requested"),
+            Self::NoState => write!(f, "No state available at move request"),
+            Self::NotResumable => write!(f, "Only an unfinished game joined with a reservation can be resumed"),
+            Self::NoGameResult => write!(f, "Failed to receive a game result"),
+            Self::AlreadySet { what } => write!(f, "The {} is already set", what),
+            Self::NotSet { what } => write!(f, "The {} is not set", what),
+            Self::ServerError { message, request: Some(request) } => write!(f, "Server error: {} (caused by {})", message, request),
+            Self::ServerError { message, request: None } => write!(f, "Server error: {}", message),
+            Self::Malformed { position, message } => write!(f, "Malformed XML at {}: {}", position, message),
+            Self::LimitExceeded { position, message } => write!(f, "Message at {} exceeds limits: {}", position, message),
+            Self::Context { context, source } => write!(f, "{}: {}", context, source),
+            Self::Eof => write!(f, "Unexpected end of input"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::ParseInt(e) => Some(e),
+            Self::ParseFloat(e) => Some(e),
+            Self::ParseBool(e) => Some(e),
+            Self::Utf8(e) => Some(e),
+            Self::Xml(e) => Some(e),
+            Self::Json(e) => Some(e),
+            Self::InvalidMove(e) => Some(e),
+            Self::InvalidAttribute { source, .. } | Self::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
 }
 
 impl From<IoError> for Error {
@@ -50,14 +145,14 @@ impl From<XmlError> for Error {
     fn from(error: XmlError) -> Self { Self::Xml(error) }
 }
 
-impl From<AttrError> for Error {
-    fn from(error: AttrError) -> Self { Self::Xml(error.into()) }
+impl From<JsonError> for Error {
+    fn from(error: JsonError) -> Self { Self::Json(error) }
 }
 
-impl From<String> for Error {
-    fn from(error: String) -> Self { Self::Custom(error) }
+impl From<AttrError> for Error {
+    fn from(error: AttrError) -> Self { Self::Xml(error.into()) }
 }
 
-impl<'a> From<&'a str> for Error {
-    fn from(error: &'a str) -> Self { Self::Custom(error.to_owned()) }
+impl From<MoveError> for Error {
+    fn from(error: MoveError) -> Self { Self::InvalidMove(error) }
 }
@@ -2,8 +2,10 @@ mod error;
 mod result;
 mod macros;
 mod xml;
+mod xml_reader;
 
 pub use error::*;
 pub use result::*;
 pub use macros::*;
 pub use xml::*;
+pub use xml_reader::*;
@@ -1,14 +1,14 @@
-use std::borrow::Cow;
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::{self, Debug};
 use std::str::{self, FromStr};
-use std::io::{Write, Cursor, BufRead};
-use log::{warn, error, info, debug, trace};
+use std::io::{Write, Cursor};
+use log::debug;
+use quick_xml::escape::escape;
 use quick_xml::events::attributes::Attribute;
 use quick_xml::events::{Event, BytesStart, BytesText, BytesEnd};
-use quick_xml::{Reader, Writer};
-use super::{Result, Error};
+use quick_xml::Writer;
+use super::{Result, Error, XmlReader};
 
 /// A deserialized, in-memory tree-representation
 /// of an XML node.
@@ -35,63 +35,6 @@ impl Element {
         ElementBuilder::new(name)
     }
 
-    /// Deserializes an XML node tree
-    /// from the given XML event reader.
-    pub fn read_from<R>(reader: &mut Reader<R>) -> Result<Element> where R: BufRead {
-        let mut node_stack = VecDeque::<Element>::new();
-        let mut buf = Vec::new();
-        
-        let element = loop {
-            match reader.read_event(&mut buf) {
-                Ok(Event::Start(ref start)) => {
-                    trace!("Read start event");
-                    let node = Element::try_from(start)?;
-                    node_stack.push_back(node);
-                },
-                Ok(Event::Empty(ref start)) => {
-                    trace!("Read empty event");
-                    let node = Element::try_from(start)?;
-                    if let Some(mut parent) = node_stack.pop_back() {
-                        parent.childs.push(node);
-                        node_stack.push_back(parent);
-                    } else {
-                        break Ok(node);
-                    }
-                },
-                Ok(Event::End(ref end)) => {
-                    trace!("Read end event");
-                    if let Some(node) = node_stack.pop_back() {
-                        if let Some(mut parent) = node_stack.pop_back() {
-                            parent.childs.push(node);
-                            node_stack.push_back(parent);
-                        } else {
-                            break Ok(node);
-                        }
-                    } else {
-                        error!("Found closing element </{}> without an opening element before", str::from_utf8(end.name())?);
-                    }
-                },
-                Ok(Event::Text(ref t)) => {
-                    trace!("Read text event");
-                    let content = str::from_utf8(t)?.trim();
-                    if !content.is_empty() {
-                        if let Some(node) = node_stack.back_mut() {
-                            node.content += content;
-                        } else {
-                            warn!("Found characters {} outside of any node", content);
-                        }
-                    }
-                },
-                Ok(Event::Eof) => break Err(Error::Eof),
-                Err(e) => break Err(e.into()),
-                ev => info!("Read other event: {:?}", ev),
-            }
-        }?;
-
-        debug!("Read {}", element);
-        Ok(element)
-    }
-    
     /// Serializes the node to an XML string using a tree traversal.
     pub fn write_to<W>(&self, writer: &mut Writer<W>) -> Result<()> where W: Write {
         self.write_to_impl(writer)?;
@@ -104,7 +47,7 @@ impl Element {
     fn write_to_impl<W>(&self, writer: &mut Writer<W>) -> Result<()> where W: Write {
         let start = BytesStart::from(self);
         
-        if self.childs.is_empty() {
+        if self.childs.is_empty() && self.content.is_empty() {
             // Write self-closing tag, e.g. <Element/>
             writer.write_event(Event::Empty(start))?;
         } else {
@@ -118,7 +61,7 @@ impl Element {
 
             // Write child elements
             for child in &self.childs {
-                child.write_to(writer)?;
+                child.write_to_impl(writer)?;
             }
             
             // Write closing tag, e.g. </Element>
@@ -140,14 +83,36 @@ impl Element {
     
     /// Fetches an attribute's value by key.
     pub fn attribute(&self, key: &str) -> Result<&str> {
-        self.attributes.get(key).map(|s| s.as_str()).ok_or_else(|| format!("No attribute with key '{}' found in <{}>!", key, self.name).into())
+        self.attributes.get(key).map(|s| s.as_str()).ok_or_else(|| Error::MissingAttribute {
+            element: self.name.clone(),
+            attribute: key.to_owned(),
+        })
+    }
+
+    /// Fetches and parses an attribute's value.
+    pub fn parse_attribute<T>(&self, key: &str) -> Result<T> where T: FromStr, T::Err: Into<Error> {
+        let value = self.attribute(key)?;
+        value.parse().map_err(|e: T::Err| Error::InvalidAttribute {
+            element: self.name.clone(),
+            attribute: key.to_owned(),
+            value: value.to_owned(),
+            source: Box::new(e.into()),
+        })
     }
     
     /// Finds the first child element with the provided tag name.
     pub fn child_by_name<'a, 'n: 'a>(&'a self, name: &'n str) -> Result<&'a Element> {
-        self.childs_by_name(name).next().ok_or_else(|| format!("No <{}> found in <{}>!", name, self.name).into())
+        self.childs_by_name(name).next().ok_or_else(|| Error::MissingChild {
+            element: self.name.clone(),
+            child: name.to_owned(),
+        })
     }
     
+    /// Fetches all child elements.
+    pub fn childs(&self) -> impl Iterator<Item=&Element> {
+        self.childs.iter()
+    }
+
     /// Fetches a list of all child elements matching the provided tag name.
     pub fn childs_by_name<'a, 'n: 'a>(&'a self, name: &'n str) -> impl Iterator<Item=&'a Element> + 'a {
         self.childs.iter().filter(move |c| c.name == name)
@@ -167,7 +132,7 @@ impl FromStr for Element {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        Element::read_from(&mut Reader::from_str(s))
+        XmlReader::new(s.as_bytes()).max_message_bytes(s.len()).read_element()
     }
 }
 
@@ -261,7 +226,7 @@ impl<'a> TryFrom<&BytesStart<'a>> for Element {
                 .map(|res| {
                     let attribute = res?;
                     let key = str::from_utf8(attribute.key)?.to_owned();
-                    let value = str::from_utf8(&attribute.value)?.to_owned();
+                    let value = str::from_utf8(&attribute.unescaped_value()?)?.to_owned();
                     Ok((key, value))
                 })
                 .collect::<Result<HashMap<_, _>>>()?,
@@ -275,23 +240,49 @@ impl<'a> From<&'a Element> for BytesStart<'a> {
         BytesStart::borrowed_name(element.name.as_bytes())
             .with_attributes(element.attributes.iter().map(|(k, v)| Attribute {
                 key: k.as_bytes(),
-                value: Cow::Borrowed(v.as_bytes()),
+                value: escape(v.as_bytes()),
             }))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Element;
+    use std::error::Error as _;
+
+    use super::{Element, Error};
 
     #[test]
     fn test_write() {
         assert_eq!("<Test/>", format!("{}", Element::new("Test").build()));
-        assert_eq!("<A><B/><C/></A>", format!("{}", Element::new("A").child(Element::new("B")).child(Element::new("C")).build()))
+        assert_eq!("<A><B/><C/></A>", format!("{}", Element::new("A").child(Element::new("B")).child(Element::new("C")).build()));
+        assert_eq!("<A><B>3</B></A>", format!("{}", Element::new("A").child(Element::new("B").content("3")).build()));
     }
 
     #[test]
     fn test_read() {
         assert_eq!("<Test/>".parse::<Element>().unwrap(), Element::new("Test").build());
     }
+
+    #[test]
+    fn test_escape_roundtrip() {
+        let element = Element::new("A").attribute("b", r#"<"&'>"#).content("1 < 2 & 3").build();
+        assert_eq!(r#"<A b="&lt;&quot;&amp;&apos;&gt;">1 &lt; 2 &amp; 3</A>"#, element.to_string());
+        assert_eq!(element.to_string().parse::<Element>().unwrap(), element);
+    }
+
+    #[test]
+    fn test_attribute_errors() {
+        let element: Element = r#"<to x="3" y="up"/>"#.parse().unwrap();
+        assert_eq!(element.parse_attribute::<i32>("x").unwrap(), 3);
+
+        let error = element.parse_attribute::<i32>("y").unwrap_err();
+        assert!(matches!(&error, Error::InvalidAttribute { element, attribute, value, source }
+            if element == "to" && attribute == "y" && value == "up" && matches!(**source, Error::ParseInt(_))));
+        assert!(error.to_string().starts_with(r#"Invalid attribute y="up" in <to>: "#));
+        assert!(error.source().and_then(|e| e.source()).is_some());
+
+        let error = element.parse_attribute::<i32>("z").unwrap_err();
+        assert!(matches!(&error, Error::MissingAttribute { element, attribute } if element == "to" && attribute == "z"));
+        assert_eq!(error.to_string(), "No attribute 'z' in <to>");
+    }
 }
@@ -0,0 +1,863 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, ErrorKind};
+use std::mem;
+use std::str;
+use log::{debug, trace};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use super::{Element, Error, Result};
+
+/// The longest tag name that is kept for matching opening and closing tags.
+const MAX_NAME_BYTES: usize = 256;
+
+/// A position in a stream of text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TextPosition {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Default for TextPosition {
+    fn default() -> Self {
+        Self::START
+    }
+}
+
+impl TextPosition {
+    /// The position of the first byte.
+    pub const START: Self = Self { offset: 0, line: 1, column: 1 };
+
+    /// The number of bytes before this position.
+    pub fn offset(self) -> usize { self.offset }
+
+    /// The line, starting at 1.
+    pub fn line(self) -> usize { self.line }
+
+    /// The column in characters, starting at 1.
+    pub fn column(self) -> usize { self.column }
+
+    fn advance(&mut self, byte: u8) {
+        self.offset += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // Continuation bytes of UTF-8 sequences do not start a new character
+            self.column += 1;
+        }
+    }
+
+    fn advanced_by(mut self, bytes: &[u8]) -> Self {
+        for &byte in bytes {
+            self.advance(byte);
+        }
+        self
+    }
+}
+
+impl fmt::Display for TextPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// A lexical unit of the stream, just detailed enough
+/// to find the boundaries of elements.
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    /// Text, with the position of its first non-whitespace character.
+    Text(Option<TextPosition>),
+    Start(Vec<u8>),
+    Empty(Vec<u8>),
+    End(Vec<u8>),
+    /// Comments, processing instructions, CDATA sections and declarations.
+    Other,
+}
+
+/// The rest of a tag after `<` or `</`.
+#[derive(Debug, Default)]
+struct Tag {
+    name: Vec<u8>,
+    after_name: bool,
+    quote: Option<u8>,
+    last: Option<u8>,
+}
+
+impl Tag {
+    /// Processes the next byte, returning whether the tag is closed.
+    fn step(&mut self, byte: u8) -> bool {
+        match self.quote {
+            Some(q) if byte == q => self.quote = None,
+            Some(_) => (),
+            None if byte == b'>' => return true,
+            None if byte == b'"' || byte == b'\'' => self.quote = Some(byte),
+            None => (),
+        }
+        if !self.after_name {
+            if byte.is_ascii_whitespace() || byte == b'/' {
+                self.after_name = true;
+            } else if self.name.len() < MAX_NAME_BYTES {
+                self.name.push(byte);
+            }
+        }
+        if !byte.is_ascii_whitespace() {
+            self.last = Some(byte);
+        }
+        false
+    }
+}
+
+/// The state of the tokenizer.
+#[derive(Debug)]
+enum Lexer {
+    /// Between tokens.
+    Idle,
+    Text(Option<TextPosition>),
+    /// After `<`.
+    Open,
+    /// After `<!`.
+    Bang,
+    /// Within a comment, CDATA section, processing instruction or declaration.
+    Skip { end: &'static [u8], matched: usize },
+    StartTag(Tag),
+    EndTag(Tag),
+}
+
+/// The position within the element structure of the stream.
+#[derive(Debug)]
+enum Frame {
+    /// Before the opening tag of the root element.
+    Prolog,
+    /// Between messages.
+    TopLevel,
+    /// Inside a message, with its open elements and the first mismatched closing tag.
+    Message { open: Vec<Vec<u8>>, mismatch: Option<Error> },
+    /// Inside a message that exceeded the limits. Elements nested beyond
+    /// the depth limit are only counted.
+    Skipping { open: Vec<Vec<u8>>, deep: usize },
+    Finished,
+}
+
+/// Decodes a stream of XML elements, such as the messages of the game
+/// server, without performing any I/O: bytes are passed in as they
+/// arrive and complete elements are taken out.
+///
+/// The decoder recovers from malformed input: every error reports the
+/// position where it occurred, and decoding continues with the next
+/// top-level element. Messages exceeding the size or nesting limits are
+/// skipped without being buffered.
+#[derive(Debug)]
+pub struct XmlDecoder {
+    /// The position of the next byte.
+    position: TextPosition,
+    max_message_bytes: usize,
+    max_depth: usize,
+    /// The element wrapping the stream, e.g. `<protocol>`.
+    root: Option<Vec<u8>>,
+    lexer: Lexer,
+    frame: Frame,
+    token_start: TextPosition,
+    message_start: TextPosition,
+    /// The raw bytes of the current message.
+    message: Vec<u8>,
+    overflowed: bool,
+    decoded: VecDeque<Result<Element>>,
+}
+
+impl Default for XmlDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl XmlDecoder {
+    /// The default limit for the size of a single message.
+    pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 1 << 20;
+    /// The default limit for the nesting of elements within a message.
+    pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+    /// Creates a decoder with the default limits.
+    pub fn new() -> Self {
+        Self {
+            position: TextPosition::START,
+            max_message_bytes: Self::DEFAULT_MAX_MESSAGE_BYTES,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            root: None,
+            lexer: Lexer::Idle,
+            frame: Frame::TopLevel,
+            token_start: TextPosition::START,
+            message_start: TextPosition::START,
+            message: Vec::new(),
+            overflowed: false,
+            decoded: VecDeque::new(),
+        }
+    }
+
+    /// Limits the size of a single message in bytes.
+    pub fn max_message_bytes(mut self, max_message_bytes: usize) -> Self {
+        self.max_message_bytes = max_message_bytes;
+        self
+    }
+
+    /// Limits the nesting of elements within a single message.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Expects the stream to be wrapped in an element with the given name,
+    /// whose children are then decoded as messages. Its closing tag ends the
+    /// stream. Must be called before any input is passed.
+    pub fn expect_root(&mut self, name: &str) {
+        self.root = Some(name.as_bytes().to_vec());
+        self.frame = Frame::Prolog;
+    }
+
+    /// Whether the opening tag of the root element has been decoded.
+    pub fn in_root(&self) -> bool {
+        self.root.is_some() && !matches!(self.frame, Frame::Prolog | Frame::Finished)
+    }
+
+    /// Whether the stream has ended.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.frame, Frame::Finished)
+    }
+
+    /// The position of the next byte.
+    pub fn position(&self) -> TextPosition { self.position }
+
+    /// Passes the next bytes of the stream.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.push(byte);
+        }
+    }
+
+    /// Signals the end of the stream.
+    pub fn finish(&mut self) {
+        match mem::replace(&mut self.lexer, Lexer::Idle) {
+            Lexer::Idle => (),
+            Lexer::Text(text_start) => self.on_token(Token::Text(text_start)),
+            _ => {
+                self.emit_error(self.token_start, "Unexpected end of input inside a tag".to_owned());
+                self.frame = Frame::Finished;
+            },
+        }
+        if let Frame::Message { open, .. } | Frame::Skipping { open, .. } = &self.frame {
+            let message = format!("Unexpected end of input inside <{}>", name_of(open));
+            self.emit_error(self.position, message);
+        }
+        self.frame = Frame::Finished;
+    }
+
+    /// Takes the next decoded element or error, `None` if more input is
+    /// needed. Returns `Error::Eof` once the stream has ended, `Error::Malformed`
+    /// for invalid XML and `Error::LimitExceeded` for messages that are too large.
+    pub fn next_element(&mut self) -> Option<Result<Element>> {
+        match self.decoded.pop_front() {
+            Some(result) => Some(result),
+            None if self.is_finished() => Some(Err(Error::Eof)),
+            None => None,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        // Text ends before the next tag
+        if byte == b'<' {
+            if let Lexer::Text(text_start) = self.lexer {
+                self.lexer = Lexer::Idle;
+                self.on_token(Token::Text(text_start));
+            }
+        }
+        if let Lexer::Idle = self.lexer {
+            self.token_start = self.position;
+            if let Frame::TopLevel = self.frame {
+                self.message.clear();
+                self.overflowed = false;
+            }
+        }
+        if let Frame::TopLevel | Frame::Message { .. } = self.frame {
+            self.buffer(byte);
+        }
+
+        let position = self.position;
+        self.position.advance(byte);
+        let token = match &mut self.lexer {
+            Lexer::Idle if byte == b'<' => {
+                self.lexer = Lexer::Open;
+                None
+            },
+            Lexer::Idle => {
+                self.lexer = Lexer::Text((!byte.is_ascii_whitespace()).then_some(position));
+                None
+            },
+            Lexer::Text(text_start) => {
+                if text_start.is_none() && !byte.is_ascii_whitespace() {
+                    *text_start = Some(position);
+                }
+                None
+            },
+            Lexer::Open => {
+                self.lexer = match byte {
+                    b'?' => Lexer::Skip { end: b"?>", matched: 0 },
+                    b'!' => Lexer::Bang,
+                    b'/' => Lexer::EndTag(Tag::default()),
+                    _ => Lexer::StartTag(Tag::default()),
+                };
+                match &mut self.lexer {
+                    Lexer::StartTag(tag) => tag.step(byte).then(|| Token::Start(mem::take(&mut tag.name))),
+                    _ => None,
+                }
+            },
+            Lexer::Bang => {
+                let end: &'static [u8] = match byte {
+                    b'-' => b"-->",
+                    b'[' => b"]]>",
+                    _ => b">",
+                };
+                self.lexer = Lexer::Skip { end, matched: 0 };
+                self.skip_step(byte)
+            },
+            Lexer::Skip { .. } => self.skip_step(byte),
+            Lexer::StartTag(tag) => tag.step(byte).then(|| {
+                let name = mem::take(&mut tag.name);
+                if tag.last == Some(b'/') { Token::Empty(name) } else { Token::Start(name) }
+            }),
+            Lexer::EndTag(tag) => tag.step(byte).then(|| Token::End(mem::take(&mut tag.name))),
+        };
+        if let Some(token) = token {
+            self.lexer = Lexer::Idle;
+            self.on_token(token);
+        }
+    }
+
+    fn skip_step(&mut self, byte: u8) -> Option<Token> {
+        let Lexer::Skip { end, matched } = &mut self.lexer else { return None };
+        *matched = if byte == end[*matched] {
+            *matched + 1
+        } else if byte == end[0] {
+            1
+        } else {
+            0
+        };
+        (*matched == end.len()).then_some(Token::Other)
+    }
+
+    fn buffer(&mut self, byte: u8) {
+        if self.overflowed {
+            return;
+        }
+        if self.message.len() < self.max_message_bytes {
+            self.message.push(byte);
+        } else {
+            // Stop buffering, the message is reported once the current token ends
+            self.overflowed = true;
+            self.message = Vec::new();
+        }
+    }
+
+    fn on_token(&mut self, token: Token) {
+        let start = self.token_start;
+        self.frame = match (mem::replace(&mut self.frame, Frame::Finished), token) {
+            (Frame::Finished, _) => Frame::Finished,
+            (Frame::Prolog, Token::Start(name)) if self.root.as_ref() == Some(&name) => {
+                debug!("Entered <{}>", String::from_utf8_lossy(&name));
+                Frame::TopLevel
+            },
+            (Frame::Prolog, Token::Empty(name)) if self.root.as_ref() == Some(&name) => Frame::Finished,
+            (Frame::Prolog, Token::Start(name) | Token::Empty(name) | Token::End(name)) => {
+                self.emit_error(start, format!("Expected <{}>, but got a tag named '{}'",
+                    name_of(self.root.as_slice()), String::from_utf8_lossy(&name)));
+                Frame::Prolog
+            },
+            (Frame::Prolog, Token::Text(Some(position))) => {
+                self.emit_error(position, format!("Expected <{}>, but got text", name_of(self.root.as_slice())));
+                Frame::Prolog
+            },
+            (Frame::Prolog, _) => Frame::Prolog,
+            (Frame::TopLevel, Token::Text(None) | Token::Other) => Frame::TopLevel,
+            (Frame::TopLevel, Token::Text(Some(position))) => {
+                self.emit_error(position, "Unexpected text outside of an element".to_owned());
+                Frame::TopLevel
+            },
+            (Frame::TopLevel, Token::End(name)) if self.root.as_ref() == Some(&name) => {
+                debug!("Left <{}>", String::from_utf8_lossy(&name));
+                Frame::Finished
+            },
+            (Frame::TopLevel, Token::End(name)) => {
+                self.emit_error(start, format!("Unexpected closing tag </{}>", String::from_utf8_lossy(&name)));
+                Frame::TopLevel
+            },
+            (Frame::TopLevel, Token::Empty(_)) => {
+                self.message_start = start;
+                self.finish_message(None);
+                Frame::TopLevel
+            },
+            (Frame::TopLevel, Token::Start(name)) => {
+                self.message_start = start;
+                self.continue_message(vec![name], None)
+            },
+            (Frame::Message { mut open, mismatch }, Token::Start(name)) => {
+                open.push(name);
+                if open.len() > self.max_depth {
+                    let message = format!("Elements are nested deeper than {} levels", self.max_depth);
+                    self.emit(Err(Error::LimitExceeded { position: start, message }));
+                    self.message = Vec::new();
+                    Frame::Skipping { open, deep: 0 }
+                } else {
+                    self.continue_message(open, mismatch)
+                }
+            },
+            (Frame::Message { mut open, mut mismatch }, Token::End(name)) => match open.iter().rposition(|n| *n == name) {
+                Some(i) => {
+                    if i + 1 < open.len() && mismatch.is_none() {
+                        mismatch = Some(malformed(start, format!("Closing tag </{}> does not match <{}>",
+                            String::from_utf8_lossy(&name), name_of(&open))));
+                    }
+                    open.truncate(i);
+                    self.continue_message(open, mismatch)
+                },
+                None if self.root.as_ref() == Some(&name) => {
+                    self.emit_error(start, format!("Unexpected end of stream inside <{}>", name_of(&open)));
+                    Frame::Finished
+                },
+                None => {
+                    mismatch.get_or_insert_with(|| malformed(start, format!("Unexpected closing tag </{}>", String::from_utf8_lossy(&name))));
+                    self.continue_message(open, mismatch)
+                },
+            },
+            (Frame::Message { open, mismatch }, _) => self.continue_message(open, mismatch),
+            (Frame::Skipping { mut open, deep }, Token::Start(name)) => {
+                if open.len() < self.max_depth {
+                    open.push(name);
+                    Frame::Skipping { open, deep }
+                } else {
+                    Frame::Skipping { open, deep: deep + 1 }
+                }
+            },
+            (Frame::Skipping { open, deep }, Token::End(_)) if deep > 0 => Frame::Skipping { open, deep: deep - 1 },
+            (Frame::Skipping { mut open, deep }, Token::End(name)) => match open.iter().rposition(|n| *n == name) {
+                Some(i) => {
+                    open.truncate(i);
+                    if open.is_empty() { Frame::TopLevel } else { Frame::Skipping { open, deep } }
+                },
+                None if self.root.as_ref() == Some(&name) => {
+                    self.emit_error(start, format!("Unexpected end of stream inside <{}>", name_of(&open)));
+                    Frame::Finished
+                },
+                None => Frame::Skipping { open, deep },
+            },
+            (frame @ Frame::Skipping { .. }, _) => frame,
+        };
+    }
+
+    /// Finishes the message once all elements are closed and
+    /// starts skipping it once it exceeds the size limit.
+    fn continue_message(&mut self, open: Vec<Vec<u8>>, mismatch: Option<Error>) -> Frame {
+        if open.is_empty() {
+            self.finish_message(mismatch);
+            Frame::TopLevel
+        } else if self.overflowed {
+            self.emit(Err(self.too_large()));
+            Frame::Skipping { open, deep: 0 }
+        } else {
+            Frame::Message { open, mismatch }
+        }
+    }
+
+    /// Parses the buffered message, unless it was found to be malformed or too large.
+    fn finish_message(&mut self, mismatch: Option<Error>) {
+        let result = if self.overflowed {
+            Err(self.too_large())
+        } else if let Some(e) = mismatch {
+            Err(e)
+        } else {
+            trace!("Decoded message of {} bytes", self.message.len());
+            let start = self.message_start;
+            parse_message(&self.message).map_err(|(offset, message)| malformed(start.advanced_by(&self.message[..offset]), message))
+        };
+        self.message.clear();
+        self.emit(result);
+    }
+
+    fn too_large(&self) -> Error {
+        Error::LimitExceeded { position: self.message_start, message: format!("Message exceeds {} bytes", self.max_message_bytes) }
+    }
+
+    fn emit_error(&mut self, position: TextPosition, message: String) {
+        self.emit(Err(malformed(position, message)));
+    }
+
+    fn emit(&mut self, result: Result<Element>) {
+        self.decoded.push_back(result);
+    }
+}
+
+/// Reads a stream of XML elements from a blocking reader,
+/// one complete element at a time, see `XmlDecoder`.
+pub struct XmlReader<R> where R: BufRead {
+    read: R,
+    decoder: XmlDecoder,
+}
+
+impl<R> XmlReader<R> where R: BufRead {
+    /// Creates a reader with the default limits.
+    pub fn new(read: R) -> Self {
+        Self { read, decoder: XmlDecoder::new() }
+    }
+
+    /// Limits the size of a single message in bytes.
+    pub fn max_message_bytes(mut self, max_message_bytes: usize) -> Self {
+        self.decoder = self.decoder.max_message_bytes(max_message_bytes);
+        self
+    }
+
+    /// Limits the nesting of elements within a single message.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.decoder = self.decoder.max_depth(max_depth);
+        self
+    }
+
+    /// The position of the next unread byte.
+    pub fn position(&self) -> TextPosition { self.decoder.position() }
+
+    /// Reads up to and including the opening tag of the element wrapping
+    /// the stream, whose children are then read as messages. Its closing
+    /// tag ends the stream.
+    pub fn read_root(&mut self, name: &str) -> Result<()> {
+        self.decoder.expect_root(name);
+        loop {
+            if let Some(Err(e)) = self.decoder.decoded.pop_front() {
+                return Err(e);
+            }
+            if self.decoder.in_root() {
+                return Ok(());
+            }
+            if self.decoder.is_finished() {
+                return Err(Error::Eof);
+            }
+            // Pass single bytes to stop right after the opening tag
+            self.fill(1)?;
+        }
+    }
+
+    /// Reads the next complete element. Returns `Error::Eof` at the end of
+    /// the stream, `Error::Malformed` for invalid XML and `Error::LimitExceeded`
+    /// for messages that are too large. After an error, the following call
+    /// continues with the next element.
+    pub fn read_element(&mut self) -> Result<Element> {
+        loop {
+            if let Some(result) = self.decoder.next_element() {
+                return result;
+            }
+            self.fill(usize::MAX)?;
+        }
+    }
+
+    /// Passes up to the given number of available bytes to the decoder.
+    fn fill(&mut self, limit: usize) -> Result<()> {
+        let buf = loop {
+            match self.read.fill_buf() {
+                Ok(buf) => break buf,
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        };
+        if buf.is_empty() {
+            self.decoder.finish();
+        } else {
+            let length = buf.len().min(limit);
+            self.decoder.feed(&buf[..length]);
+            self.read.consume(length);
+        }
+        Ok(())
+    }
+}
+
+fn malformed(position: TextPosition, message: String) -> Error {
+    Error::Malformed { position, message }
+}
+
+fn name_of(open: &[Vec<u8>]) -> String {
+    open.last().map(|n| String::from_utf8_lossy(n).into_owned()).unwrap_or_default()
+}
+
+/// An element whose closing tag has not been read yet.
+struct OpenElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    content: String,
+    childs: Vec<Element>,
+}
+
+impl OpenElement {
+    fn build(self) -> Element {
+        Element::new(&self.name)
+            .attributes(self.attributes)
+            .content(&self.content)
+            .childs(self.childs)
+            .build()
+    }
+}
+
+impl TryFrom<&BytesStart<'_>> for OpenElement {
+    type Error = Error;
+
+    fn try_from(start: &BytesStart<'_>) -> Result<Self> {
+        Ok(Self {
+            name: str::from_utf8(start.name())?.to_owned(),
+            attributes: start.attributes()
+                .map(|res| {
+                    let attribute = res?;
+                    let key = str::from_utf8(attribute.key)?.to_owned();
+                    let value = str::from_utf8(&attribute.unescaped_value()?)?.to_owned();
+                    Ok((key, value))
+                })
+                .collect::<Result<_>>()?,
+            content: String::new(),
+            childs: Vec::new(),
+        })
+    }
+}
+
+/// Parses a single complete element, returning the offset and
+/// description of the first error.
+fn parse_message(bytes: &[u8]) -> std::result::Result<Element, (usize, String)> {
+    let mut reader = Reader::from_reader(bytes);
+    let mut buf = Vec::new();
+    let mut open: Vec<OpenElement> = Vec::new();
+    loop {
+        let offset = reader.buffer_position();
+        let event = reader.read_event(&mut buf).map_err(|e| (reader.buffer_position(), e.to_string()))?;
+        let describe = |e: Error| (offset, e.to_string());
+        let node = match event {
+            Event::Start(ref start) => {
+                open.push(OpenElement::try_from(start).map_err(describe)?);
+                continue;
+            },
+            Event::Empty(ref start) => OpenElement::try_from(start).map_err(describe)?.build(),
+            Event::End(_) => match open.pop() {
+                Some(node) => node.build(),
+                None => return Err((offset, "Unexpected closing tag".to_owned())),
+            },
+            Event::Text(ref text) => {
+                let content = text.unescaped().map_err(|e| (offset, e.to_string()))?;
+                let content = str::from_utf8(&content).map_err(|e| (offset, e.to_string()))?.trim();
+                add_content(&mut open, content).map_err(|m| (offset, m))?;
+                continue;
+            },
+            Event::CData(ref data) => {
+                let content = str::from_utf8(data).map_err(|e| (offset, e.to_string()))?;
+                add_content(&mut open, content).map_err(|m| (offset, m))?;
+                continue;
+            },
+            Event::Eof => return Err((offset, "Unexpected end of message".to_owned())),
+            _ => continue,
+        };
+        match open.last_mut() {
+            Some(parent) if !parent.content.is_empty() => return Err((offset, format!("Mixed text and elements in <{}>", parent.name))),
+            Some(parent) => parent.childs.push(node),
+            None => return Ok(node),
+        }
+    }
+}
+
+fn add_content(open: &mut [OpenElement], content: &str) -> std::result::Result<(), String> {
+    if content.is_empty() {
+        return Ok(());
+    }
+    match open.last_mut() {
+        Some(node) if !node.childs.is_empty() => Err(format!("Mixed text and elements in <{}>", node.name)),
+        Some(node) => {
+            node.content += content;
+            Ok(())
+        },
+        None => Err("Unexpected text outside of an element".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use crate::util::{Element, Error};
+
+    use super::{XmlDecoder, XmlReader};
+
+    fn reader(input: &str) -> XmlReader<&[u8]> {
+        let mut reader = XmlReader::new(input.as_bytes());
+        reader.read_root("protocol").unwrap();
+        reader
+    }
+
+    /// Reads all elements, returning their names or the
+    /// line and column of the errors.
+    fn read_all(reader: &mut XmlReader<&[u8]>) -> Vec<std::result::Result<String, (usize, usize)>> {
+        let mut results = Vec::new();
+        loop {
+            match reader.read_element() {
+                Ok(element) => results.push(Ok(element.name().to_owned())),
+                Err(Error::Eof) => return results,
+                Err(Error::Malformed { position, .. } | Error::LimitExceeded { position, .. }) => results.push(Err((position.line(), position.column()))),
+                Err(e) => panic!("Unexpected error {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_stream() {
+        let mut reader = reader(indoc! {r#"
+            <?xml version="1.0"?>
+            <protocol>
+                <joined roomId="a"/>
+                <!-- A comment -->
+                <room roomId="a">
+                    <data class="welcomeMessage" color="ONE"/>
+                </room>
+                <text><![CDATA[<raw & text>]]></text>
+            </protocol>
+            <ignored/>
+        "#});
+        assert_eq!(reader.read_element().unwrap(), Element::new("joined").attribute("roomId", "a").build());
+        assert_eq!(reader.read_element().unwrap(), Element::new("room")
+            .attribute("roomId", "a")
+            .child(Element::new("data").attribute("class", "welcomeMessage").attribute("color", "ONE"))
+            .build());
+        assert_eq!(reader.read_element().unwrap().content(), "<raw & text>");
+        assert!(matches!(reader.read_element(), Err(Error::Eof)));
+        assert!(matches!(reader.read_element(), Err(Error::Eof)));
+    }
+
+    #[test]
+    fn test_resync() {
+        let mut reader = reader(indoc! {r#"
+            <protocol>
+                <a><b></a>
+                <a x="1" x="2"/>
+                </b>
+                stray text
+                <a>text<b/></a>
+                <a><b/><c></b></c></a>
+                <a x=1/>
+                <done/>
+        "#});
+        assert_eq!(read_all(&mut reader), vec![
+            Err((2, 11)),
+            Err((3, 5)),
+            Err((4, 5)),
+            Err((5, 5)),
+            Err((6, 12)),
+            Err((7, 15)),
+            Err((8, 5)),
+            Ok("done".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert_eq!(read_all(&mut reader("<protocol>\n<a>\n<b>text</b>\n<c")), vec![Err((4, 1))]);
+        assert_eq!(read_all(&mut reader("<protocol>\n<a>\n<b>text</b>\n</protocol>")), vec![Err((4, 1))]);
+    }
+
+    #[test]
+    fn test_limits() {
+        let input = format!("<protocol><a>{}</a><b/><a>{}</a><c/>", "x".repeat(1000), "<a>".repeat(10) + &"</a>".repeat(10));
+        let mut reader = XmlReader::new(input.as_bytes()).max_message_bytes(100).max_depth(5);
+        reader.read_root("protocol").unwrap();
+        assert!(matches!(reader.read_element(), Err(Error::LimitExceeded { position, .. }) if position.offset() == 10));
+        assert_eq!(reader.read_element().unwrap().name(), "b");
+        assert!(matches!(reader.read_element(), Err(Error::LimitExceeded { .. })));
+        assert_eq!(reader.read_element().unwrap().name(), "c");
+        assert!(matches!(reader.read_element(), Err(Error::Eof)));
+    }
+
+    #[test]
+    fn test_root() {
+        let mut reader = XmlReader::new(&b"<other/>"[..]);
+        assert!(matches!(reader.read_root("protocol"), Err(Error::Malformed { .. })));
+        let mut reader = XmlReader::new(&b"  "[..]);
+        assert!(matches!(reader.read_root("protocol"), Err(Error::Eof)));
+    }
+
+    #[test]
+    fn test_fuzz() {
+        let valid = indoc! {r#"
+            <protocol>
+                <joined roomId="a"/>
+                <room roomId="a"><data class="welcomeMessage" color="ONE"/></room>
+                <room roomId="a">
+                    <data class="memento">
+                        <state class="state" turn="1">
+                            <startTeam>ONE</startTeam>
+                            <fishes><int>1</int><int>0</int></fishes>
+                        </state>
+                    </data>
+                </room>
+                <left roomId="a"/>
+            </protocol>
+        "#};
+        let expected = read_all(&mut reader(valid));
+        assert_eq!(expected.len(), 4);
+        assert!(expected.iter().all(|r| r.is_ok()));
+
+        const JUNK: &[&[u8]] = &[b"<", b">", b"/", b"\"", b"&", b"<!--", b"]]>", b"<![CDATA[", b"</a>", b"<a>", b"\xff", b"\n"];
+        let mut rng = StdRng::seed_from_u64(44);
+        for _ in 0..2000 {
+            let mut input = valid.as_bytes().to_vec();
+            for _ in 0..rng.gen_range(1..4) {
+                let i = rng.gen_range(0..=input.len());
+                match rng.gen_range(0..3) {
+                    0 => { input.splice(i..i, JUNK[rng.gen_range(0..JUNK.len())].iter().copied()); },
+                    1 if i < input.len() => { input.remove(i); },
+                    _ => input.truncate(i),
+                }
+            }
+
+            let mut reader = XmlReader::new(&input[..]).max_message_bytes(200);
+            if reader.read_root("protocol").is_err() {
+                continue;
+            }
+            let mut results = Vec::new();
+            loop {
+                match reader.read_element() {
+                    Ok(element) => {
+                        assert_eq!(element.to_string().parse::<Element>().unwrap(), element);
+                        results.push(Ok(element));
+                    },
+                    Err(Error::Eof) => break,
+                    Err(Error::Malformed { position, .. } | Error::LimitExceeded { position, .. }) => {
+                        assert!(position.offset() <= input.len());
+                        results.push(Err(position));
+                    },
+                    Err(e) => panic!("Unexpected error {:?}", e),
+                }
+            }
+
+            // Decoding does not depend on how the input is split
+            let mut decoder = XmlDecoder::new().max_message_bytes(200);
+            decoder.expect_root("protocol");
+            let mut rest = &input[..];
+            let mut chunked = Vec::new();
+            loop {
+                match decoder.next_element() {
+                    Some(Err(Error::Eof)) => break,
+                    Some(result) => chunked.push(result),
+                    None if rest.is_empty() => decoder.finish(),
+                    None => {
+                        let (chunk, remaining) = rest.split_at(rng.gen_range(1..=rest.len().min(50)));
+                        decoder.feed(chunk);
+                        rest = remaining;
+                    },
+                }
+            }
+            let chunked: Vec<_> = chunked.into_iter().map(|r| r.map_err(|e| match e {
+                Error::Malformed { position, .. } | Error::LimitExceeded { position, .. } => position,
+                e => panic!("Unexpected error {:?}", e),
+            })).collect();
+            assert_eq!(chunked, results, "Results differ on {}", String::from_utf8_lossy(&input));
+        }
+    }
+}